@@ -0,0 +1,240 @@
+//! Conversions between R date-time classes and Rust types.
+//!
+
+use crate::args::*;
+use crate::robj::*;
+use crate::{append_lang, lang};
+
+/// Broken-down calendar time, as stored in an R `POSIXlt` object.
+///
+/// Unlike R, the month is counted from 1 and the year is the full year.
+/// The fields are in the time zone of the R object.
+///
+/// Example:
+/// ```
+/// use extendr_api::*;
+/// start_r();
+/// let t = POSIXlt { year: 2020, month: 2, day: 29, hour: 13, min: 45, sec: 30. };
+/// let robj = Robj::from(t.clone());
+/// assert!(robj.inherits("POSIXlt"));
+/// assert_eq!(robj.as_posixlt(), Some(t));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct POSIXlt {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub min: u32,
+    pub sec: f64,
+}
+
+impl Robj {
+    /// Read the first time of a `POSIXlt` object.
+    /// Returns None if this is not a `POSIXlt` or the time is NA.
+    pub fn as_posixlt(&self) -> Option<POSIXlt> {
+        if !self.inherits("POSIXlt") {
+            return None;
+        }
+        let names = self.getAttrib(&Robj::namesSymbol());
+        let component = |name: &str| -> Option<f64> {
+            let (_, value) = names
+                .str_iter()?
+                .zip(self.list_iter()?)
+                .find(|(n, _)| *n == name)?;
+            if value.len() == 0 {
+                return None;
+            }
+            let value = value.asReal();
+            if value.is_nan() {
+                None
+            } else {
+                Some(value)
+            }
+        };
+        Some(POSIXlt {
+            year: component("year")? as i32 + 1900,
+            month: component("mon")? as u32 + 1,
+            day: component("mday")? as u32,
+            hour: component("hour")? as u32,
+            min: component("min")? as u32,
+            sec: component("sec")?,
+        })
+    }
+}
+
+impl<'a> FromRobj<'a> for POSIXlt {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if let Some(t) = robj.as_posixlt() {
+            Ok(t)
+        } else {
+            Err("expected a POSIXlt time")
+        }
+    }
+}
+
+/// Make a `POSIXlt` object in the UTC time zone.
+impl From<POSIXlt> for Robj {
+    fn from(t: POSIXlt) -> Self {
+        let ct = lang!(
+            "ISOdatetime",
+            t.year,
+            t.month,
+            t.day,
+            t.hour,
+            t.min,
+            t.sec,
+            tz = "UTC"
+        )
+        .eval_blind();
+        let ct = unsafe { ct.get() };
+        lang!("as.POSIXlt", ct).eval_blind()
+    }
+}
+
+// Read the first element of a Date or POSIXct object as a double.
+#[cfg(feature = "chrono")]
+fn first_number(robj: &Robj) -> Result<f64, &'static str> {
+    if robj.len() == 0 {
+        return Err("zero length vector");
+    }
+    let value = robj.asReal();
+    if value.is_nan() {
+        Err("must not be NA")
+    } else {
+        Ok(value)
+    }
+}
+
+/// Convert a `Date`, stored as days since 1970-01-01.
+#[cfg(feature = "chrono")]
+impl<'a> FromRobj<'a> for chrono::NaiveDate {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if !robj.inherits("Date") {
+            return Err("expected a Date");
+        }
+        let days = first_number(robj)?.floor() as i64;
+        let days = chrono::TimeDelta::try_days(days).ok_or("Date out of range")?;
+        chrono::NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .checked_add_signed(days)
+            .ok_or("Date out of range")
+    }
+}
+
+/// Convert a `POSIXct`, stored as seconds since 1970-01-01 UTC.
+#[cfg(feature = "chrono")]
+impl<'a> FromRobj<'a> for chrono::DateTime<chrono::Utc> {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        use chrono::TimeZone;
+        if !robj.inherits("POSIXct") {
+            return Err("expected a POSIXct time");
+        }
+        let time = first_number(robj)?;
+        let secs = time.floor();
+        let nanos = ((time - secs) * 1e9).round().min(999_999_999.) as u32;
+        chrono::Utc
+            .timestamp_opt(secs as i64, nanos)
+            .single()
+            .ok_or("POSIXct time out of range")
+    }
+}
+
+/// Make a `Date` object.
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDate> for Robj {
+    fn from(date: chrono::NaiveDate) -> Self {
+        let epoch = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        Robj::from((date - epoch).num_days() as f64).with_class("Date")
+    }
+}
+
+/// Make a `POSIXct` object in the UTC time zone.
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Robj {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        let secs = time.timestamp() as f64 + time.timestamp_subsec_nanos() as f64 * 1e-9;
+        let mut robj = Robj::from(secs);
+        robj.setAttrib(&Robj::classSymbol(), Robj::from(vec!["POSIXct", "POSIXt"]));
+        robj.setAttrib(&Robj::from("tzone"), Robj::from("UTC"));
+        robj
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::*;
+
+    #[test]
+    fn test_posixlt() {
+        start_r();
+        let t = POSIXlt {
+            year: 2020,
+            month: 2,
+            day: 29,
+            hour: 13,
+            min: 45,
+            sec: 30.5,
+        };
+        let robj = Robj::from(t.clone());
+        assert!(robj.inherits("POSIXlt"));
+        assert_eq!(robj.as_posixlt(), Some(t.clone()));
+        assert_eq!(<POSIXlt>::from_robj(&robj), Ok(t));
+
+        let robj = Robj::eval_string("as.POSIXlt('1999-12-31 23:59:59', tz = 'UTC')").unwrap();
+        assert_eq!(
+            robj.as_posixlt(),
+            Some(POSIXlt {
+                year: 1999,
+                month: 12,
+                day: 31,
+                hour: 23,
+                min: 59,
+                sec: 59.
+            })
+        );
+
+        assert_eq!(Robj::from(1).as_posixlt(), None);
+        assert!(<POSIXlt>::from_robj(&Robj::from(1)).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        use chrono::{NaiveDate, TimeZone, Utc};
+        start_r();
+        let date = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+        let robj = Robj::from(date);
+        assert!(robj.inherits("Date"));
+        assert_eq!(<NaiveDate>::from_robj(&robj), Ok(date));
+        let robj = Robj::eval_string("as.Date('1969-12-31')").unwrap();
+        assert_eq!(
+            <NaiveDate>::from_robj(&robj),
+            Ok(NaiveDate::from_ymd_opt(1969, 12, 31).unwrap())
+        );
+
+        let time = Utc.timestamp_opt(1_583_000_000, 500_000_000).unwrap();
+        let robj = Robj::from(time);
+        assert!(robj.inherits("POSIXct"));
+        assert_eq!(<chrono::DateTime<Utc>>::from_robj(&robj), Ok(time));
+        let robj = Robj::eval_string("as.POSIXct('2000-01-01 12:00:00', tz = 'UTC')").unwrap();
+        assert_eq!(
+            <chrono::DateTime<Utc>>::from_robj(&robj),
+            Ok(Utc.timestamp_opt(946_728_000, 0).unwrap())
+        );
+
+        let robj = Robj::eval_string("as.Date(Inf)").unwrap();
+        assert_eq!(<NaiveDate>::from_robj(&robj), Err("Date out of range"));
+        let robj = Robj::eval_string("as.Date(1e10)").unwrap();
+        assert_eq!(<NaiveDate>::from_robj(&robj), Err("Date out of range"));
+        let robj = Robj::eval_string("as.POSIXct(Inf, origin = '1970-01-01')").unwrap();
+        assert_eq!(
+            <chrono::DateTime<Utc>>::from_robj(&robj),
+            Err("POSIXct time out of range")
+        );
+
+        assert!(<NaiveDate>::from_robj(&Robj::from(1.)).is_err());
+        assert!(<chrono::DateTime<Utc>>::from_robj(&Robj::from(1.)).is_err());
+    }
+}
@@ -21,22 +21,49 @@ macro_rules! cstr_mut {
 
 static mut R_ERROR_BUF: Vec<u8> = Vec::new();
 
-/// Throw an R error if a result is an error.
-pub fn unwrap_or_throw<T>(r: Result<T, &'static str>) -> T {
+/// Throw an R error with a message.
+/// R unwinds the stack to the caller, so this function does not return.
+pub fn throw_r_error<S: AsRef<str>>(s: S) -> ! {
     unsafe {
-        match r {
-            Err(e) => {
-                R_ERROR_BUF.clear();
-                R_ERROR_BUF.extend(e.bytes());
-                R_ERROR_BUF.push(0);
-                Rf_error(R_ERROR_BUF.as_slice().as_ptr() as *mut raw::c_char);
-                unreachable!("");
-            }
-            Ok(v) => v,
+        R_ERROR_BUF.clear();
+        R_ERROR_BUF.extend(s.as_ref().bytes());
+        R_ERROR_BUF.push(0);
+        // Destructors are not run when R unwinds the stack.
+        drop(s);
+        Rf_error(
+            cstr_mut!("%s"),
+            R_ERROR_BUF.as_slice().as_ptr() as *mut raw::c_char,
+        );
+        unreachable!("");
+    }
+}
+
+/// Throw an R error if a result is an error.
+pub fn unwrap_or_throw<T, E: std::fmt::Display>(r: Result<T, E>) -> T {
+    match r {
+        Err(e) => {
+            let msg = e.to_string();
+            drop(e);
+            throw_r_error(msg)
         }
+        Ok(v) => v,
     }
 }
 
+/// Run a closure, returning the panic message as an error if it panics.
+/// Used by the `#[extendr]` wrappers, as unwinding into R is undefined behaviour.
+pub fn catch_panic<F: FnOnce() -> T, T>(f: F) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        if let Some(s) = payload.downcast_ref::<&str>() {
+            format!("Rust panic: {}", s)
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            format!("Rust panic: {}", s)
+        } else {
+            "Rust panic".to_string()
+        }
+    })
+}
+
 static START_R: Once = Once::new();
 
 pub fn start_r() {
@@ -99,4 +126,17 @@ mod tests {
         // So avoid doing this in tests.
         //end_r();
     }
+
+    #[test]
+    fn test_catch_panic() {
+        assert_eq!(catch_panic(|| 1), Ok(1));
+        assert_eq!(
+            catch_panic(|| -> i32 { panic!("oops") }),
+            Err("Rust panic: oops".to_string())
+        );
+        assert_eq!(
+            catch_panic(|| -> i32 { panic!("bad value {}", 42) }),
+            Err("Rust panic: bad value 42".to_string())
+        );
+    }
 }
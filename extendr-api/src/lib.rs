@@ -63,6 +63,7 @@
 //!
 
 mod args;
+mod datetime;
 mod engine;
 mod logical;
 mod rmacros;
@@ -70,6 +71,7 @@ mod robj;
 mod wrapper;
 
 pub use args::*;
+pub use datetime::*;
 pub use engine::*;
 pub use rmacros::*;
 pub use robj::*;
@@ -124,6 +126,19 @@ pub unsafe fn register_call_methods(info: *mut libR_sys::DllInfo, methods: &[Cal
     //libR_sys::R_forceSymbols(info, 1);
 }
 
+// Internal function used to implement nested modules in extendr_module!.
+// Inserts the module prefix after the wrapper prefix, eg. wrap__add -> wrap__math__add.
+// Only the registered name changes; the C symbol of the wrapper is still wrap__add.
+pub fn prefix_call_methods(methods: &mut [CallMethod], wrap_prefix: &str, prefix: &str) {
+    for m in methods {
+        let name = m.call_symbol.to_str().unwrap();
+        if let Some(rest) = name.strip_prefix(wrap_prefix) {
+            let name = format!("{}{}{}", wrap_prefix, prefix, rest);
+            m.call_symbol = CString::new(name).unwrap();
+        }
+    }
+}
+
 // pub fn add_function_to_namespace(namespace: &str, fn_name: &str, wrap_name: &str) {
 //     let rcode = format!("{}::{} <- function(...) .Call(\"{}\", ...)", namespace, fn_name, wrap_name);
 //     eprintln!("[{}]", rcode);
@@ -174,6 +189,26 @@ mod tests {
         assert_eq!(b, "def");
     }
 
+    #[extendr]
+    pub fn optional_sum(x: Option<&[f64]>) -> f64 {
+        x.map(|x| x.iter().sum()).unwrap_or(0.)
+    }
+
+    #[extendr]
+    pub fn squares(out: &mut [f64]) {
+        for (i, x) in out.iter_mut().enumerate() {
+            *x = (i * i) as f64;
+        }
+    }
+
+    #[extendr]
+    pub fn checked_positive(x: i32) -> i32 {
+        if x <= 0 {
+            panic!("{} is not positive", x);
+        }
+        x
+    }
+
     #[extendr]
     pub fn vectortypes(a: Vec<i32>, b: Vec<f64>) {
         assert_eq!(a, [1, 2, 3]);
@@ -235,12 +270,24 @@ mod tests {
         123.
     }
 
+    #[extendr]
+    pub fn return_result(a: i32) -> Result<i32, &'static str> {
+        if a > 0 {
+            Ok(a)
+        } else {
+            Err("expected a positive number")
+        }
+    }
+
+    #[derive(Debug)]
     struct Person {
         pub name: String,
     }
 
-    #[extendr]
+    #[extendr(r_print_debug)]
     impl Person {
+        const VERSION: i32 = 3;
+
         fn new() -> Self {
             Self {
                 name: "".to_string(),
@@ -254,16 +301,125 @@ mod tests {
         fn name(&self) -> &str {
             self.name.as_str()
         }
+
+        fn renamed(mut self, name: &str) -> Self {
+            self.name = name.to_string();
+            self
+        }
+
+        fn with_name(name: &str) -> Result<Self, String> {
+            if name.is_empty() {
+                Err("a person must have a name".to_string())
+            } else {
+                Ok(Self {
+                    name: name.to_string(),
+                })
+            }
+        }
     }
 
     #[extendr]
     fn aux_func(_person: &Person) {}
 
+    // A free function returning a type exported by an impl.
+    #[extendr]
+    fn make_person(name: &str) -> Person {
+        Person {
+            name: name.to_string(),
+        }
+    }
+
+    #[extendr(expose_fields)]
+    struct Point {
+        pub x: f64,
+        pub y: f64,
+        #[allow(dead_code)]
+        label: String,
+    }
+
+    #[extendr]
+    impl Point {
+        fn new(x: f64, y: f64) -> Self {
+            Self {
+                x,
+                y,
+                label: String::new(),
+            }
+        }
+    }
+
+    struct Numbers {
+        values: Vec<f64>,
+    }
+
+    #[extendr(r_length, r_index)]
+    impl Numbers {
+        fn new(values: Vec<f64>) -> Self {
+            Self { values }
+        }
+    }
+
+    impl Numbers {
+        fn len(&self) -> usize {
+            self.values.len()
+        }
+
+        fn get(&self, i: usize) -> Option<Robj> {
+            self.values.get(i).map(|&x| Robj::from(x))
+        }
+    }
+
+    mod math {
+        use super::*;
+
+        #[extendr]
+        pub fn add(a: i32, b: i32) -> i32 {
+            a + b
+        }
+    }
+
     // Macro to generate exports
     extendr_module! {
         mod my_module;
         fn aux_func;
         impl Person;
+        impl Point;
+        struct Point;
+        mod math {
+            fn add;
+        }
+    }
+
+    // Call a one argument wrapper from R with .Call, giving the message of any R error it throws.
+    // An external pointer that .Call accepts as a native routine.
+    fn native_symbol(wrapper: *mut u8) -> Robj {
+        start_r();
+        let tag = Robj::from(Symbol("native symbol"));
+        unsafe { Robj::makeExternalPtr(wrapper, tag, Robj::from(())) }
+    }
+
+    fn call_wrapper(wrapper: extern "C" fn(SEXP) -> SEXP, arg: &Robj) -> Result<Robj, String> {
+        let fptr = native_symbol(wrapper as *mut u8);
+        let (fptr, arg) = unsafe { (fptr.get(), arg.get()) };
+        let dot_call = lang!(".Call", fptr, arg);
+        let on_error = Robj::eval_string(
+            "function(e) structure(conditionMessage(e), class = \"error_message\")",
+        )
+        .unwrap();
+        let (call, handler) = unsafe { (dot_call.get(), on_error.get()) };
+        let res = lang!("tryCatch", call, error = handler).eval().unwrap();
+        if res.inherits("error_message") {
+            Err(res.as_str().unwrap().to_string())
+        } else {
+            Ok(res)
+        }
+    }
+
+    // Bind a value to a name in the global environment.
+    fn assign_global(name: &str, value: &Robj) {
+        let name = Robj::from(name);
+        let (name, value) = unsafe { (name.get(), value.get()) };
+        lang!("assign", name, value).eval().unwrap();
     }
 
     #[test]
@@ -309,9 +465,151 @@ mod tests {
             assert_eq!(new_borrowed(wrap__return_i64()), Robj::from(123));
             assert_eq!(new_borrowed(wrap__return_f32()), Robj::from(123.));
             assert_eq!(new_borrowed(wrap__return_f64()), Robj::from(123.));
+            assert_eq!(
+                new_borrowed(wrap__return_result(Robj::from(1).get())),
+                Robj::from(1)
+            );
+            assert_eq!(
+                call_wrapper(wrap__return_result, &Robj::from(-1)),
+                Err("expected a positive number".to_string())
+            );
+            assert_eq!(
+                new_borrowed(wrap__optional_sum(Robj::from(&[1., 2.][..]).get())),
+                Robj::from(3.)
+            );
+            assert_eq!(
+                new_borrowed(wrap__optional_sum(Robj::from(()).get())),
+                Robj::from(0.)
+            );
+
+            let person = new_borrowed(wrap__Person__with_name(Robj::from("fred").get()));
+            assert!(person.check_external_ptr("Person"));
+            assert_eq!(
+                call_wrapper(wrap__Person__with_name, &Robj::from("")),
+                Err("a person must have a name".to_string())
+            );
+            assert_eq!(
+                new_borrowed(wrap__Person__name(person.get())),
+                Robj::from("fred")
+            );
+            assert_eq!(new_borrowed(wrap__Person__VERSION()), Robj::from(3));
+            assert_eq!(
+                new_borrowed(wrap__Person__print(person.get())),
+                Robj::from("Person { name: \"fred\" }")
+            );
+
+            let renamed =
+                new_borrowed(wrap__Person__renamed(person.get(), Robj::from("bob").get()));
+            assert_eq!(
+                new_borrowed(wrap__Person__name(renamed.get())),
+                Robj::from("bob")
+            );
+            assert!(person.take_external_ptr::<Person>().is_none());
+            assert_eq!(
+                call_wrapper(wrap__Person__name, &person),
+                Err("Person has already been moved".to_string())
+            );
+
+            let person = new_borrowed(wrap__make_person(Robj::from("jim").get()));
+            assert!(person.check_external_ptr("Person"));
+            assert_eq!(
+                new_borrowed(wrap__Person__name(person.get())),
+                Robj::from("jim")
+            );
         }
     }
 
+    #[test]
+    fn panic_test() {
+        assert_eq!(
+            call_wrapper(wrap__checked_positive, &Robj::from(1)),
+            Ok(Robj::from(1))
+        );
+        assert_eq!(
+            call_wrapper(wrap__checked_positive, &Robj::from(-1)),
+            Err("Rust panic: -1 is not positive".to_string())
+        );
+    }
+
+    #[test]
+    fn expose_fields_test() {
+        start_r();
+        unsafe {
+            let p = new_borrowed(wrap__Point__new(Robj::from(1.).get(), Robj::from(2.).get()));
+            assert_eq!(new_borrowed(wrap__Point__x(p.get())), Robj::from(1.));
+            assert_eq!(new_borrowed(wrap__Point__y(p.get())), Robj::from(2.));
+        }
+    }
+
+    #[test]
+    fn collection_test() {
+        start_r();
+        unsafe {
+            let x = new_borrowed(wrap__Numbers__new(Robj::from(&[1., 2., 3.][..]).get()));
+            assign_global("numbers", &x.with_class("Numbers"));
+        }
+        let length = native_symbol(wrap__Numbers__length as *mut u8);
+        let index = native_symbol(wrap__Numbers__index as *mut u8);
+        assign_global("numbers_length", &length);
+        assign_global("numbers_index", &index);
+        Robj::eval_string("length.Numbers <- function(x) .Call(numbers_length, x)").unwrap();
+        Robj::eval_string("`[[.Numbers` <- function(x, i) .Call(numbers_index, x, i)").unwrap();
+
+        // Dispatch the S3 methods from R.
+        assert_eq!(Robj::eval_string("length(numbers)").unwrap(), Robj::from(3));
+        assert_eq!(Robj::eval_string("numbers[[1]]").unwrap(), Robj::from(1.));
+        assert_eq!(Robj::eval_string("numbers[[3]]").unwrap(), Robj::from(3.));
+        assert_eq!(
+            Robj::eval_string("tryCatch(numbers[[0]], error = conditionMessage)").unwrap(),
+            Robj::from("subscript out of bounds")
+        );
+        assert_eq!(
+            Robj::eval_string("tryCatch(numbers[[4]], error = conditionMessage)").unwrap(),
+            Robj::from("subscript out of bounds")
+        );
+    }
+
+    #[test]
+    fn output_buffer_test() {
+        start_r();
+        let out = Robj::eval_string("numeric(4)").unwrap();
+        unsafe {
+            wrap__squares(out.get());
+        }
+        assert_eq!(out.as_f64_slice(), Some(&[0., 1., 4., 9.][..]));
+    }
+
+    #[test]
+    fn take_external_ptr_test() {
+        start_r();
+        let mut person = Person::new();
+        person.set_name("fred");
+        let robj = Robj::from(person);
+        let person = unsafe { robj.take_external_ptr::<Person>() };
+        assert_eq!(person.map(|p| p.name), Some("fred".to_string()));
+        assert!(unsafe { robj.take_external_ptr::<Person>() }.is_none());
+
+        // The finalizer must not free the value again.
+        drop(robj);
+        lang!("gc").eval_blind();
+    }
+
+    #[test]
+    fn module_test() {
+        let names: Vec<String> = get_my_module_call_methods(std::ptr::null_mut())
+            .iter()
+            .map(|m| m.call_symbol.to_str().unwrap().to_string())
+            .collect();
+        assert!(names.contains(&"wrap__aux_func".to_string()));
+        assert!(names.contains(&"wrap__Person__new".to_string()));
+        assert!(names.contains(&"wrap__Person__VERSION".to_string()));
+        assert!(names.contains(&"wrap__Person__print".to_string()));
+        assert!(names.contains(&"wrap__Point__x".to_string()));
+        assert!(!names.contains(&"wrap__Point__label".to_string()));
+        assert!(names.contains(&"wrap__math__add".to_string()));
+        assert!(!names.contains(&"wrap__add".to_string()));
+    }
+
     #[test]
     fn r_output_test() {
         let fifo = lang!("fifo", Robj::from("")).eval().unwrap();
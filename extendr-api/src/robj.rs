@@ -11,9 +11,11 @@
 use libR_sys::*;
 use std::os::raw;
 
+use crate::args::*;
 use crate::logical::*;
 use crate::wrapper::*;
 use crate::AnyError;
+use crate::{append_lang, lang};
 
 use ndarray::prelude::*;
 
@@ -58,19 +60,26 @@ pub trait FromRobj<'a>: Sized {
     }
 }
 
+// The length is checked before the type, so any empty vector is a zero length error.
+// Integer targets reject NA, float targets keep it as NaN.
+// Logical vectors are not accepted as numbers.
 macro_rules! impl_prim_from_robj {
-    ($t: ty) => {
+    ($t: ty, $is_integer: expr) => {
         impl<'a> FromRobj<'a> for $t {
             fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
-                if let Some(v) = robj.as_i32_slice() {
-                    if v.len() == 0 {
-                        Err("zero length vector")
-                    } else {
+                if robj.len() == 0 {
+                    Err("zero length vector")
+                } else if let Some(v) = robj.as_i32_slice() {
+                    if v[0] != unsafe { R_NaInt } {
                         Ok(v[0] as Self)
+                    } else if $is_integer {
+                        Err("must not be NA")
+                    } else {
+                        Ok(unsafe { R_NaReal } as Self)
                     }
                 } else if let Some(v) = robj.as_f64_slice() {
-                    if v.len() == 0 {
-                        Err("zero length vector")
+                    if $is_integer && unsafe { R_IsNA(v[0]) } != 0 {
+                        Err("must not be NA")
                     } else {
                         Ok(v[0] as Self)
                     }
@@ -82,53 +91,224 @@ macro_rules! impl_prim_from_robj {
     };
 }
 
-impl_prim_from_robj!(u8);
-impl_prim_from_robj!(u16);
-impl_prim_from_robj!(u32);
-impl_prim_from_robj!(u64);
-impl_prim_from_robj!(i8);
-impl_prim_from_robj!(i16);
-impl_prim_from_robj!(i32);
-impl_prim_from_robj!(i64);
-impl_prim_from_robj!(f32);
-impl_prim_from_robj!(f64);
-
-impl<'a> FromRobj<'a> for &'a str {
+impl_prim_from_robj!(u8, true);
+impl_prim_from_robj!(u16, true);
+impl_prim_from_robj!(u32, true);
+impl_prim_from_robj!(u64, true);
+impl_prim_from_robj!(i8, true);
+impl_prim_from_robj!(i16, true);
+impl_prim_from_robj!(i32, true);
+impl_prim_from_robj!(i64, true);
+impl_prim_from_robj!(f32, false);
+impl_prim_from_robj!(f64, false);
+
+impl<'a> FromRobj<'a> for bool {
     fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
-        if let Some(s) = robj.as_str() {
-            Ok(s)
+        if let Some(v) = robj.as_logical_slice() {
+            if v.len() == 0 {
+                Err("zero length vector")
+            } else if v[0].0 == unsafe { R_NaInt } {
+                Err("must not be NA")
+            } else {
+                Ok(v[0].0 != 0)
+            }
         } else {
-            Err("not a string object")
+            Err("not a logical object")
         }
     }
 }
 
+// Check the length before NA so that character(0) is not reported as NA.
+fn str_from_robj(robj: &Robj) -> Result<&str, &'static str> {
+    if robj.sexptype() == STRSXP {
+        if robj.len() == 0 {
+            return Err("zero length vector");
+        } else if unsafe { STRING_ELT(robj.get(), 0) == R_NaString } {
+            return Err("must not be NA");
+        }
+    }
+    robj.as_str().ok_or("not a string object")
+}
+
+impl<'a> FromRobj<'a> for &'a str {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        str_from_robj(robj)
+    }
+}
+
 impl<'a> FromRobj<'a> for String {
     fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
-        if let Some(s) = robj.as_str() {
-            Ok(s.to_string())
-        } else {
-            Err("not a string object")
+        str_from_robj(robj).map(|s| s.to_string())
+    }
+}
+
+/// Convert a string containing a single character.
+impl<'a> FromRobj<'a> for char {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if robj.sexptype() != STRSXP {
+            return Err("not a string object");
+        }
+        let mut chars = str_from_robj(robj)?.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err("expected a single character"),
+        }
+    }
+}
+
+/// Parse a UUID from a character scalar.
+#[cfg(feature = "uuid")]
+impl<'a> FromRobj<'a> for uuid::Uuid {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if robj.sexptype() != STRSXP || robj.len() != 1 {
+            return Err("expected a character scalar");
+        }
+        if unsafe { STRING_ELT(robj.get(), 0) == R_NaString } {
+            return Err("NA is not a valid UUID");
+        }
+        uuid::Uuid::parse_str(robj.as_str().unwrap()).map_err(|_| "not a valid UUID")
+    }
+}
+
+/// Convert a complex scalar. NA is converted with NaN parts.
+#[cfg(feature = "num-complex")]
+impl<'a> FromRobj<'a> for num_complex::Complex<f64> {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        match <Vec<num_complex::Complex<f64>>>::from_robj(robj)?.as_slice() {
+            [v] => Ok(*v),
+            _ => Err("expected a complex scalar"),
+        }
+    }
+}
+
+/// Convert a complex vector. NA elements are converted with NaN parts.
+#[cfg(feature = "num-complex")]
+impl<'a> FromRobj<'a> for Vec<num_complex::Complex<f64>> {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if robj.sexptype() != CPLXSXP {
+            return Err("expected a complex vector");
+        }
+        unsafe {
+            let slice = std::slice::from_raw_parts(COMPLEX(robj.get()), robj.len());
+            Ok(slice
+                .iter()
+                .map(|c| num_complex::Complex::new(c.r, c.i))
+                .collect())
         }
     }
 }
 
+/// Copy an integer vector. NA elements are copied as `i32::MIN`;
+/// use `Robj::try_into_vec_reporting` to reject them.
 impl<'a> FromRobj<'a> for Vec<i32> {
     fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
         if let Some(v) = robj.as_i32_slice() {
             Ok(Vec::from(v))
         } else {
-            Err("not an integer or logical vector")
+            Err(AsTypedSlice::<i32>::typed_slice_mismatch(robj))
         }
     }
 }
 
+/// Copy a double vector. NA elements are copied as R's NA NaN;
+/// use `Robj::try_into_vec_reporting` to reject them.
 impl<'a> FromRobj<'a> for Vec<f64> {
     fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
         if let Some(v) = robj.as_f64_slice() {
             Ok(Vec::from(v))
         } else {
-            Err("not a floating point vector")
+            Err(AsTypedSlice::<f64>::typed_slice_mismatch(robj))
+        }
+    }
+}
+
+/// Checked conversion of integer and double vector elements,
+/// used by `Robj::try_into_vec_reporting`.
+pub trait NumericElement: Sized {
+    fn from_i32(value: i32) -> Option<Self>;
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+macro_rules! impl_integer_element {
+    ($t: ty) => {
+        impl NumericElement for $t {
+            fn from_i32(value: i32) -> Option<Self> {
+                use std::convert::TryFrom;
+                Self::try_from(value).ok()
+            }
+
+            fn from_f64(value: f64) -> Option<Self> {
+                // MAX + 1 is a power of two, so it is exact as f64 even where MAX is not.
+                let upper = Self::MAX as f64 + 1.0;
+                if value.fract() == 0.0 && value >= Self::MIN as f64 && value < upper {
+                    Some(value as Self)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+impl_integer_element!(u8);
+impl_integer_element!(u16);
+impl_integer_element!(u32);
+impl_integer_element!(u64);
+impl_integer_element!(i8);
+impl_integer_element!(i16);
+impl_integer_element!(i32);
+impl_integer_element!(i64);
+
+macro_rules! impl_float_element {
+    ($t: ty) => {
+        impl NumericElement for $t {
+            fn from_i32(value: i32) -> Option<Self> {
+                Some(value as Self)
+            }
+
+            fn from_f64(value: f64) -> Option<Self> {
+                Some(value as Self)
+            }
+        }
+    };
+}
+
+impl_float_element!(f32);
+impl_float_element!(f64);
+
+/// Copy the bytes of a raw vector.
+#[cfg(feature = "bytes")]
+impl<'a> FromRobj<'a> for bytes::Bytes {
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if let Some(v) = robj.as_raw_bytes() {
+            Ok(bytes::Bytes::copy_from_slice(v))
+        } else {
+            Err("not a raw vector")
+        }
+    }
+}
+
+/// Input vector parameter, eg. `&[f64]` or `&[i32]`.
+impl<'a, T> FromRobj<'a> for &'a [T]
+where
+    Robj: AsTypedSlice<T>,
+{
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        robj.as_typed_slice()
+            .ok_or_else(|| AsTypedSlice::<T>::typed_slice_mismatch(robj))
+    }
+}
+
+/// Optional parameter. NULL converts to None.
+impl<'a, T> FromRobj<'a> for Option<T>
+where
+    T: FromRobj<'a>,
+{
+    fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
+        if robj.isNull() {
+            Ok(None)
+        } else {
+            T::from_robj(robj).map(Some)
         }
     }
 }
@@ -144,7 +324,7 @@ where
         if let Some(v) = robj.as_typed_slice() {
             Ok(ArrayView1::<'a, T>::from(v))
         } else {
-            Err("not a floating point vector")
+            Err(AsTypedSlice::<T>::typed_slice_mismatch(robj))
         }
     }
 }
@@ -207,11 +387,57 @@ impl Robj {
         unsafe { TYPEOF(self.get()) as u32 }
     }
 
+    /// Get the name of the type of the object, as returned by R's typeof().
+    pub fn type_name(&self) -> &'static str {
+        match self.sexptype() {
+            NILSXP => "NULL",
+            SYMSXP => "symbol",
+            LISTSXP => "pairlist",
+            CLOSXP => "closure",
+            ENVSXP => "environment",
+            PROMSXP => "promise",
+            LANGSXP => "language",
+            SPECIALSXP => "special",
+            BUILTINSXP => "builtin",
+            CHARSXP => "char",
+            LGLSXP => "logical",
+            INTSXP => "integer",
+            REALSXP => "double",
+            CPLXSXP => "complex",
+            STRSXP => "character",
+            DOTSXP => "...",
+            ANYSXP => "any",
+            VECSXP => "list",
+            EXPRSXP => "expression",
+            BCODESXP => "bytecode",
+            EXTPTRSXP => "externalptr",
+            WEAKREFSXP => "weakref",
+            RAWSXP => "raw",
+            S4SXP => "S4",
+            _ => "unknown",
+        }
+    }
+
     /// Get the extended length of the object.
     pub fn len(&self) -> usize {
         unsafe { Rf_xlength(self.get()) as usize }
     }
 
+    /// Get a read-only reference to the content of a vector,
+    /// with an error like "expected double, got integer" if it has another type.
+    pub fn try_as_typed_slice<T>(&self) -> Result<&[T], AnyError>
+    where
+        Self: AsTypedSlice<T>,
+    {
+        self.as_typed_slice().ok_or_else(|| {
+            AnyError::from(format!(
+                "expected {}, got {}",
+                <Self as AsTypedSlice<T>>::TYPE_NAME,
+                self.type_name()
+            ))
+        })
+    }
+
     /// Get a read-only reference to the content of an integer or logical vector.
     pub fn as_i32_slice(&self) -> Option<&[i32]> {
         self.as_typed_slice()
@@ -227,11 +453,21 @@ impl Robj {
         self.as_typed_slice()
     }
 
-    /// Get a read-only reference to the content of an integer or logical vector.
+    /// Get a read-only reference to the content of a raw vector.
     pub fn as_u8_slice(&self) -> Option<&[u8]> {
         self.as_typed_slice()
     }
 
+    /// Get the bytes of a raw vector, eg. the result of R's `serialize(x, NULL)`.
+    /// Returns None for any other type; use `as.raw` in R to convert first.
+    pub fn as_raw_bytes(&self) -> Option<&[u8]> {
+        if self.sexptype() == RAWSXP {
+            self.as_u8_slice()
+        } else {
+            None
+        }
+    }
+
     /// Get a read-write reference to the content of an integer or logical vector.
     pub fn as_i32_slice_mut(&mut self) -> Option<&mut [i32]> {
         self.as_typed_slice_mut()
@@ -378,9 +614,533 @@ impl Robj {
             _ => false,
         }
     }
+
+    /// Compute the mean of a numeric vector weighted by a vector of the same length.
+    /// If na_rm is true, NA elements are dropped along with their weights,
+    /// otherwise an NA element makes the result NA.
+    pub fn weighted_mean(&self, weights: &Robj, na_rm: bool) -> Result<f64, AnyError> {
+        let x = to_f64_vec(self).ok_or("weighted_mean: not a numeric vector")?;
+        let w = to_f64_vec(weights).ok_or("weighted_mean: weights are not a numeric vector")?;
+        if x.len() != w.len() {
+            return Err(AnyError::from(
+                "weighted_mean: weights must have the same length as the vector",
+            ));
+        }
+        let mut sum = 0.0;
+        let mut sum_weights = 0.0;
+        for (&x, &w) in x.iter().zip(w.iter()) {
+            if na_rm && x.is_nan() {
+                continue;
+            }
+            // Like R, zero weights do not contribute even if x is infinite.
+            if w != 0.0 {
+                sum += x * w;
+            }
+            sum_weights += w;
+        }
+        Ok(sum / sum_weights)
+    }
+
+    /// Compute the covariance matrix of the columns of a numeric matrix using R's cov().
+    pub fn cov(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("cov: not a numeric matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("cov", x).eval()
+    }
+
+    /// Compute the correlation matrix of the columns of a numeric matrix using R's cor().
+    pub fn cor(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("cor: not a numeric matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("cor", x).eval()
+    }
+
+    /// Compute the QR decomposition of a numeric matrix using R's qr().
+    /// The result is a list with elements qr, rank, qraux and pivot.
+    pub fn qr(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("qr: not a numeric matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("qr", x).eval()
+    }
+
+    /// Compute the upper triangular Cholesky factor of a positive definite matrix using R's chol().
+    pub fn chol(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("chol: not a numeric matrix"));
+        }
+        if self.nrows() != self.ncols() {
+            return Err(AnyError::from("chol: not a square matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("chol", x)
+            .eval()
+            .map_err(|_| AnyError::from("chol: the matrix is not positive definite"))
+    }
+
+    /// Compute the eigenvalues and eigenvectors of a square numeric matrix using R's eigen().
+    /// The result is a list with elements values and vectors.
+    pub fn eigen(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("eigen: not a numeric matrix"));
+        }
+        if self.nrows() != self.ncols() {
+            return Err(AnyError::from("eigen: not a square matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("eigen", x).eval()
+    }
+
+    /// Compute the determinant of a square numeric matrix using R's det().
+    /// This calls back into R, so like the other R calls it must be made from the R thread.
+    pub fn det(&self) -> Result<f64, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("det: not a numeric matrix"));
+        }
+        if self.nrows() != self.ncols() {
+            return Err(AnyError::from("det: not a square matrix"));
+        }
+        let x = unsafe { self.get() };
+        let det = lang!("det", x).eval()?;
+        det.as_f64_slice()
+            .and_then(|v| v.first().cloned())
+            .ok_or_else(|| AnyError::from("det: expected a number"))
+    }
+
+    /// Compute the inverse of a square numeric matrix using R's solve().
+    /// This calls back into R, so like the other R calls it must be made from the R thread.
+    pub fn inverse(&self) -> Result<Robj, AnyError> {
+        if !self.isMatrix() || !self.isNumeric() {
+            return Err(AnyError::from("inverse: not a numeric matrix"));
+        }
+        if self.nrows() != self.ncols() {
+            return Err(AnyError::from("inverse: not a square matrix"));
+        }
+        let x = unsafe { self.get() };
+        lang!("solve", x)
+            .eval()
+            .map_err(|_| AnyError::from("inverse: the matrix is singular"))
+    }
+
+    /// Interleave vectors of the same type and length element by element,
+    /// so that `c(1, 3, 5)` and `c(2, 4, 6)` become `c(1, 2, 3, 4, 5, 6)`.
+    pub fn interleave(vectors: &[&Robj]) -> Result<Robj, AnyError> {
+        let first = vectors.first().ok_or("interleave: no vectors given")?;
+        let sexptype = first.sexptype();
+        let len = first.len();
+        match sexptype {
+            LGLSXP | INTSXP | REALSXP | CPLXSXP | RAWSXP | STRSXP | VECSXP => (),
+            _ => return Err(AnyError::from("interleave: not a vector")),
+        }
+        for v in vectors {
+            if v.sexptype() != sexptype {
+                return Err(AnyError::from(
+                    "interleave: vectors must have the same type",
+                ));
+            }
+            if v.len() != len {
+                return Err(AnyError::from(
+                    "interleave: vectors must have the same length",
+                ));
+            }
+        }
+        let n = vectors.len();
+        unsafe {
+            let res = new_owned(Rf_allocVector(sexptype, (len * n) as R_xlen_t));
+            let sexp = res.get();
+            for (k, v) in vectors.iter().enumerate() {
+                let src = v.get();
+                for j in 0..len {
+                    let (i, j) = ((j * n + k) as R_xlen_t, j as R_xlen_t);
+                    match sexptype {
+                        LGLSXP => *LOGICAL(sexp).offset(i) = *LOGICAL(src).offset(j),
+                        INTSXP => *INTEGER(sexp).offset(i) = *INTEGER(src).offset(j),
+                        REALSXP => *REAL(sexp).offset(i) = *REAL(src).offset(j),
+                        CPLXSXP => *COMPLEX(sexp).offset(i) = *COMPLEX(src).offset(j),
+                        RAWSXP => *RAW(sexp).offset(i) = *RAW(src).offset(j),
+                        STRSXP => SET_STRING_ELT(sexp, i, STRING_ELT(src, j)),
+                        _ => {
+                            SET_VECTOR_ELT(sexp, i, VECTOR_ELT(src, j));
+                        }
+                    }
+                }
+            }
+            Ok(res)
+        }
+    }
+
+    /// Convert an integer or double vector to a Vec, checking every element.
+    /// On failure, the error gives the (one based) index and value of the first bad element.
+    pub fn try_into_vec_reporting<T: NumericElement>(&self) -> Result<Vec<T>, AnyError> {
+        if let Some(v) = self.as_i32_slice() {
+            v.iter()
+                .enumerate()
+                .map(|(i, &x)| {
+                    if x == unsafe { R_NaInt } {
+                        Err(AnyError::from(format!("element {} is NA", i + 1)))
+                    } else {
+                        T::from_i32(x).ok_or_else(|| {
+                            AnyError::from(format!("element {} ({}) is out of range", i + 1, x))
+                        })
+                    }
+                })
+                .collect()
+        } else if let Some(v) = self.as_f64_slice() {
+            v.iter()
+                .enumerate()
+                .map(|(i, &x)| {
+                    if unsafe { R_IsNA(x) } != 0 {
+                        Err(AnyError::from(format!("element {} is NA", i + 1)))
+                    } else {
+                        T::from_f64(x).ok_or_else(|| {
+                            AnyError::from(format!("element {} ({}) is out of range", i + 1, x))
+                        })
+                    }
+                })
+                .collect()
+        } else {
+            Err(AnyError::from(format!(
+                "expected an integer or double vector, got {}",
+                self.type_name()
+            )))
+        }
+    }
+
+    /// Make a factor from one based integer codes and a vector of levels.
+    /// Every code must be NA or index one of the levels, and the levels must be unique.
+    pub fn new_factor(codes: &[i32], levels: &[&str]) -> Result<Robj, AnyError> {
+        let na = unsafe { R_NaInt };
+        for (i, &code) in codes.iter().enumerate() {
+            if code != na && (code < 1 || code as usize > levels.len()) {
+                return Err(AnyError::from(format!(
+                    "new_factor: code {} at element {} is not a level",
+                    code,
+                    i + 1
+                )));
+            }
+        }
+        let mut seen = std::collections::HashSet::with_capacity(levels.len());
+        for level in levels {
+            if !seen.insert(level) {
+                return Err(AnyError::from(format!(
+                    "new_factor: duplicated level {}",
+                    level
+                )));
+            }
+        }
+        let mut res = Robj::from(codes);
+        res.setAttrib(&Robj::levelsSymbol(), Robj::from(levels));
+        Ok(res.with_class("factor"))
+    }
+
+    /// Make a list from an iterator of Robjs.
+    /// The elements are written straight into a list that doubles in size when full.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// let list = Robj::list_from_iter((1..=3).map(Robj::from));
+    /// assert_eq!(list, List(&[Robj::from(1), Robj::from(2), Robj::from(3)]));
+    /// ```
+    pub fn list_from_iter<I: IntoIterator<Item = Robj>>(iter: I) -> Robj {
+        let iter = iter.into_iter();
+        let mut list = unsafe { new_owned(Rf_allocVector(VECSXP, iter.size_hint().0 as R_xlen_t)) };
+        let mut len = 0;
+        for value in iter {
+            unsafe {
+                if len == list.len() {
+                    let grown = new_owned(Rf_allocVector(VECSXP, (len * 2).max(8) as R_xlen_t));
+                    for i in 0..len as R_xlen_t {
+                        SET_VECTOR_ELT(grown.get(), i, VECTOR_ELT(list.get(), i));
+                    }
+                    list = grown;
+                }
+                SET_VECTOR_ELT(list.get(), len as R_xlen_t, value.get());
+            }
+            len += 1;
+        }
+        if len == list.len() {
+            list
+        } else {
+            list.xlengthgets(len).unwrap()
+        }
+    }
+
+    /// Make a named list from an iterator of (name, value) pairs.
+    /// The names are written into a character vector as the list is filled.
+    pub fn named_list_from_iter<I: IntoIterator<Item = (String, Robj)>>(iter: I) -> Robj {
+        let iter = iter.into_iter();
+        let mut names = StringsBuilder::with_capacity(iter.size_hint().0);
+        let mut res = Robj::list_from_iter(iter.map(|(name, value)| {
+            names.push(&name);
+            value
+        }));
+        res.setAttrib(&Robj::namesSymbol(), names.build());
+        res
+    }
+
+    /// Make a factor from a character vector, with the levels in order of first appearance.
+    /// NA elements get an NA code and are not levels.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// let f = Robj::from(&["b", "a", "b"][..]).to_factor().unwrap();
+    /// assert_eq!(f, Robj::new_factor(&[1, 2, 1], &["b", "a"]).unwrap());
+    /// ```
+    pub fn to_factor(&self) -> Result<Robj, AnyError> {
+        let iter = self.str_iter().ok_or_else(|| {
+            AnyError::from(format!(
+                "to_factor: expected a character vector, got {}",
+                self.type_name()
+            ))
+        })?;
+        let mut levels: Vec<&str> = Vec::new();
+        let mut lookup = std::collections::HashMap::new();
+        let codes: Vec<i32> = iter
+            .options()
+            .map(|s| match s {
+                Some(s) => *lookup.entry(s).or_insert_with(|| {
+                    levels.push(s);
+                    levels.len() as i32
+                }),
+                None => unsafe { R_NaInt },
+            })
+            .collect();
+        Robj::new_factor(&codes, &levels)
+    }
+
+    /// Get the integer code of element index of a factor.
+    /// Returns None if this is not a factor, the index is out of range or the element is NA.
+    pub fn factor_code_of(&self, index: usize) -> Option<i32> {
+        if self.sexptype() != INTSXP || !self.inherits("factor") {
+            return None;
+        }
+        let code = *self.as_i32_slice()?.get(index)?;
+        if code == unsafe { R_NaInt } {
+            None
+        } else {
+            Some(code)
+        }
+    }
+
+    /// Get the level label of element index of a factor.
+    /// Returns None if this is not a factor, the index is out of range or the element is NA.
+    pub fn factor_level_of(&self, index: usize) -> Option<&str> {
+        let code = self.factor_code_of(index)?;
+        let levels = self.getAttrib(&Robj::levelsSymbol());
+        let mut iter = levels.str_iter()?;
+        iter.nth((code as usize).checked_sub(1)?)
+    }
+
+    /// Iterate over the (code, level label) pairs of a factor in one pass.
+    /// NA elements give the NA code with the label "NA".
+    pub fn factor_iter(&self) -> Option<impl Iterator<Item = (i32, &str)>> {
+        if self.sexptype() != INTSXP || !self.inherits("factor") {
+            return None;
+        }
+        let levels: Vec<&str> = self.getAttrib(&Robj::levelsSymbol()).str_iter()?.collect();
+        let na = unsafe { R_NaInt };
+        Some(self.as_i32_slice()?.iter().map(move |&code| {
+            let label = if code == na {
+                None
+            } else {
+                (code as usize).checked_sub(1).and_then(|i| levels.get(i))
+            };
+            (code, label.copied().unwrap_or("NA"))
+        }))
+    }
+
+    /// Parse each element of a character vector to a double, like `as.numeric`.
+    /// NA and blank strings become NA. Other elements that do not parse become NA,
+    /// or an error if warn is true.
+    /// Unlike R, hexadecimal input such as "0x1A" does not parse.
+    pub fn as_numeric_parsed(&self, warn: bool) -> Result<Robj, AnyError> {
+        if self.sexptype() != STRSXP {
+            return Err(AnyError::from(format!(
+                "as_numeric_parsed: expected a character vector, got {}",
+                self.type_name()
+            )));
+        }
+        let mut values = Vec::with_capacity(self.len());
+        let mut failed = false;
+        for (elt, s) in self.str_iter().unwrap().enumerate() {
+            let is_na = unsafe { STRING_ELT(self.get(), elt as R_xlen_t) == R_NaString };
+            let s = s.trim();
+            let value = if is_na || s.is_empty() || s == "NA" {
+                Some(unsafe { R_NaReal })
+            } else {
+                s.parse::<f64>().ok()
+            };
+            values.push(value.unwrap_or_else(|| {
+                failed = true;
+                unsafe { R_NaReal }
+            }));
+        }
+        if warn && failed {
+            return Err(AnyError::from(
+                "as_numeric_parsed: NAs introduced by coercion",
+            ));
+        }
+        Ok(Robj::from(&values[..]))
+    }
+
+    /// Convert a logical, integer, double or factor vector to a character vector,
+    /// like `as.character`. Doubles use up to 15 significant digits and NA stays NA.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// let x = Robj::from(&[1.5, 100000.0, 0.1][..]);
+    /// assert_eq!(x.as_character().unwrap(), Robj::from(&["1.5", "1e+05", "0.1"][..]));
+    /// ```
+    pub fn as_character(&self) -> Result<Robj, AnyError> {
+        let na = unsafe { R_NaInt };
+        let strings: Vec<Option<String>> = if let Some(pairs) = self.factor_iter() {
+            pairs
+                .map(|(code, label)| {
+                    if code == na {
+                        None
+                    } else {
+                        Some(label.to_string())
+                    }
+                })
+                .collect()
+        } else {
+            match self.sexptype() {
+                STRSXP => return Ok(self.clone()),
+                LGLSXP => self
+                    .as_logical_slice()
+                    .unwrap()
+                    .iter()
+                    .map(|b| match b.0 {
+                        0 => Some("FALSE".to_string()),
+                        x if x == na => None,
+                        _ => Some("TRUE".to_string()),
+                    })
+                    .collect(),
+                INTSXP => self
+                    .as_i32_slice()
+                    .unwrap()
+                    .iter()
+                    .map(|&x| if x == na { None } else { Some(x.to_string()) })
+                    .collect(),
+                REALSXP => self
+                    .as_f64_slice()
+                    .unwrap()
+                    .iter()
+                    .map(|&x| {
+                        if unsafe { R_IsNA(x) } != 0 {
+                            None
+                        } else {
+                            Some(format_double(x))
+                        }
+                    })
+                    .collect(),
+                _ => {
+                    return Err(AnyError::from(format!(
+                        "as_character: expected an atomic vector, got {}",
+                        self.type_name()
+                    )))
+                }
+            }
+        };
+        unsafe {
+            let res = new_owned(Rf_allocVector(STRSXP, strings.len() as R_xlen_t));
+            for (i, s) in strings.iter().enumerate() {
+                let elt = match s {
+                    Some(s) => Rf_mkCharLen(s.as_ptr() as *const raw::c_char, s.len() as i32),
+                    None => R_NaString,
+                };
+                SET_STRING_ELT(res.get(), i as R_xlen_t, elt);
+            }
+            Ok(res)
+        }
+    }
+
+    /// Get the output of R's print() as a string, with lines separated by newlines.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// assert_eq!(Robj::from(&[1, 2][..]).format().unwrap(), "[1] 1 2");
+    /// ```
+    pub fn format(&self) -> Result<String, AnyError> {
+        // Quote the object so that print does not evaluate symbols and calls.
+        let x = unsafe { self.get() };
+        let quote_call = lang!("quote", x);
+        let quoted = unsafe { quote_call.get() };
+        let print_call = lang!("print", quoted);
+        let call = unsafe { print_call.get() };
+        let lines = lang!("capture.output", call).eval()?;
+        let lines: Vec<&str> = lines
+            .str_iter()
+            .ok_or("format: capture.output did not return a character vector")?
+            .collect();
+        Ok(lines.join("\n"))
+    }
+}
+
+// Format a double like as.character: 15 significant digits, using scientific
+// notation when it is narrower than fixed notation.
+fn format_double(x: f64) -> String {
+    if x.is_nan() {
+        return "NaN".to_string();
+    } else if x.is_infinite() {
+        return if x > 0.0 { "Inf" } else { "-Inf" }.to_string();
+    } else if x == 0.0 {
+        return "0".to_string();
+    }
+    let sci = format!("{:.14e}", x);
+    let (mantissa, exponent) = sci.split_at(sci.find('e').unwrap());
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let digits = mantissa.chars().filter(|c| c.is_ascii_digit()).count() as i32;
+    let decimals = (digits - 1 - exponent).max(0) as usize;
+    let fixed = format!("{:.*}", decimals, x);
+    let sci = format!(
+        "{}e{}{:02}",
+        mantissa,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    );
+    if fixed.len() <= sci.len() {
+        fixed
+    } else {
+        sci
+    }
+}
+
+// Copy an integer or double vector to a Vec<f64>, mapping integer NA to NaN.
+fn to_f64_vec(robj: &Robj) -> Option<Vec<f64>> {
+    if let Some(v) = robj.as_f64_slice() {
+        Some(Vec::from(v))
+    } else if let Some(v) = robj.as_i32_slice() {
+        Some(
+            v.iter()
+                .map(|&i| {
+                    if i == i32::MIN {
+                        std::f64::NAN
+                    } else {
+                        i as f64
+                    }
+                })
+                .collect(),
+        )
+    } else {
+        None
+    }
 }
 
 pub trait AsTypedSlice<T> {
+    /// The R type name of vectors that give a slice of T, eg. "double".
+    const TYPE_NAME: &'static str;
+
+    /// The error used when the vector has a different type, eg. "expected double, got integer".
+    /// This is a static string so that it can be used by FromRobj.
+    fn typed_slice_mismatch(&self) -> &'static str;
+
     fn as_typed_slice(&self) -> Option<&[T]> {
         None
     }
@@ -390,8 +1150,24 @@ pub trait AsTypedSlice<T> {
 }
 
 macro_rules! make_typed_slice {
-    ($type: ty, $fn: tt, $($sexp: tt),* ) => {
+    ($type: ty, $fn: tt, $name: tt, $($sexp: tt),* ) => {
         impl AsTypedSlice<$type> for Robj {
+            const TYPE_NAME: &'static str = $name;
+
+            fn typed_slice_mismatch(&self) -> &'static str {
+                match self.sexptype() {
+                    NILSXP => concat!("expected ", $name, ", got NULL"),
+                    LGLSXP => concat!("expected ", $name, ", got logical"),
+                    INTSXP => concat!("expected ", $name, ", got integer"),
+                    REALSXP => concat!("expected ", $name, ", got double"),
+                    CPLXSXP => concat!("expected ", $name, ", got complex"),
+                    STRSXP => concat!("expected ", $name, ", got character"),
+                    VECSXP => concat!("expected ", $name, ", got list"),
+                    RAWSXP => concat!("expected ", $name, ", got raw"),
+                    _ => concat!("expected ", $name, " vector"),
+                }
+            }
+
             fn as_typed_slice(&self) -> Option<&[$type]> {
                 match self.sexptype() {
                     $( $sexp )|* => {
@@ -419,10 +1195,10 @@ macro_rules! make_typed_slice {
     }
 }
 
-make_typed_slice!(Bool, INTEGER, LGLSXP);
-make_typed_slice!(i32, INTEGER, INTSXP);
-make_typed_slice!(f64, REAL, REALSXP);
-make_typed_slice!(u8, RAW, RAWSXP);
+make_typed_slice!(Bool, INTEGER, "logical", LGLSXP);
+make_typed_slice!(i32, INTEGER, "integer", INTSXP);
+make_typed_slice!(f64, REAL, "double", REALSXP);
+make_typed_slice!(u8, RAW, "raw", RAWSXP);
 
 ///////////////////////////////////////////////////////////////
 /// The following impls wrap specific Rinternals.h symbols.
@@ -722,10 +1498,31 @@ impl Robj {
     }
 
     /// Allocate a matrix object (see NumericMatrix etc.)
+    /// Like R, the elements of logical, integer, double, complex and raw matrices
+    /// are not initialized. Use zeros_matrix to fill them with zero.
     pub fn allocMatrix(sexptype: SEXPTYPE, rows: i32, cols: i32) -> Robj {
         unsafe { new_owned(Rf_allocMatrix(sexptype, rows, cols)) }
     }
 
+    /// Allocate a matrix object with every element set to zero (or FALSE).
+    /// Character and list matrices are already filled with "" and NULL by R.
+    pub fn zeros_matrix(sexptype: SEXPTYPE, rows: i32, cols: i32) -> Robj {
+        let res = Robj::allocMatrix(sexptype, rows, cols);
+        let len = res.len();
+        unsafe {
+            let sexp = res.get();
+            match sexptype {
+                LGLSXP => std::ptr::write_bytes(LOGICAL(sexp), 0, len),
+                INTSXP => std::ptr::write_bytes(INTEGER(sexp), 0, len),
+                REALSXP => std::ptr::write_bytes(REAL(sexp), 0, len),
+                CPLXSXP => std::ptr::write_bytes(COMPLEX(sexp), 0, len),
+                RAWSXP => std::ptr::write_bytes(RAW(sexp), 0, len),
+                _ => (),
+            }
+        }
+        res
+    }
+
     /* TODO:
     int Rf_asLogical2(SEXP x, int checking, SEXP call, SEXP rho);
     Rcomplex Rf_asComplex(SEXP x);
@@ -781,7 +1578,15 @@ impl Robj {
     SEXP Rf_findVar(SEXP, SEXP);
     SEXP Rf_findVarInFrame(SEXP, SEXP);
     SEXP Rf_findVarInFrame3(SEXP, SEXP, Rboolean);
-    SEXP Rf_getAttrib(SEXP, SEXP);
+    */
+
+    /// Get an attribute of an object, eg. `x.getAttrib(&Robj::namesSymbol())`.
+    /// Returns NULL if the attribute is not present.
+    pub fn getAttrib(&self, name: &Robj) -> Robj {
+        unsafe { new_owned(Rf_getAttrib(self.get(), name.get())) }
+    }
+
+    /*
     SEXP Rf_GetArrayDimnames(SEXP);
     SEXP Rf_GetColNames(SEXP);
     void Rf_GetMatrixDimnames(SEXP, SEXP*, SEXP*, const char**, const char**);
@@ -826,13 +1631,41 @@ impl Robj {
         unsafe { Rf_nrows(self.get()) as usize }
     }
 
+    /// Set an attribute of an object, eg. `x.setAttrib(&Robj::classSymbol(), Robj::from("Date"))`.
+    /// The name may be a symbol or a string.
+    pub fn setAttrib(&mut self, name: &Robj, value: Robj) {
+        unsafe {
+            Rf_setAttrib(self.get(), name.get(), value.get());
+        }
+    }
+
+    /// Remove all attributes, including class, dim and names.
+    /// The object is modified in place, so this affects every reference to it.
+    pub fn clear_attributes(&mut self) {
+        unsafe {
+            SET_ATTRIB(self.get(), R_NilValue);
+            SET_OBJECT(self.get(), 0);
+        }
+    }
+
+    /// Set the class attribute, eg. to make an external pointer dispatch S3 methods.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// let robj = Robj::from(1).with_class("myclass");
+    /// assert!(robj.inherits("myclass"));
+    /// ```
+    pub fn with_class(mut self, class: &str) -> Self {
+        self.setAttrib(&Robj::classSymbol(), Robj::from(class));
+        self
+    }
+
     /*SEXP Rf_nthcdr(SEXP, int);
     Rboolean Rf_pmatch(SEXP, SEXP, Rboolean);
     Rboolean Rf_psmatch(const char *, const char *, Rboolean);
     void Rf_PrintValue(SEXP);
     void Rf_printwhere(void);
     void Rf_readS3VarsFromFrame(SEXP, SEXP*, SEXP*, SEXP*, SEXP*, SEXP*, SEXP*);
-    SEXP Rf_setAttrib(SEXP, SEXP, SEXP);
     void Rf_setSVector(SEXP*, int, SEXP);
     void Rf_setVar(SEXP, SEXP, SEXP);
     SEXP Rf_stringSuffix(SEXP, int);
@@ -887,12 +1720,21 @@ impl Robj {
         R_RegisterCFinalizer(self.get(), func);
     }
 
+    /// Clear the address of an external pointer, setting it to NULL.
+    pub unsafe fn clearExternalPtr(&self) {
+        R_ClearExternalPtr(self.get());
+    }
+
+    /// Replace the object kept alive by an external pointer.
+    /// Use this to keep an R object alive for as long as the Rust data that refers to it.
+    pub unsafe fn setExternalPtrProtected(&self, prot: Robj) {
+        R_SetExternalPtrProtected(self.get(), prot.get());
+    }
+
     // SEXP R_ExternalPtrTag(SEXP s);
     // SEXP R_ExternalPtrProtected(SEXP s);
-    // void R_ClearExternalPtr(SEXP s);
     // void R_SetExternalPtrAddr(SEXP s, void *p);
     // void R_SetExternalPtrTag(SEXP s, SEXP tag);
-    // void R_SetExternalPtrProtected(SEXP s, SEXP p);
 
     /*
     SEXP R_MakeWeakRef(SEXP key, SEXP val, SEXP fin, Rboolean onexit);
@@ -925,10 +1767,27 @@ impl Robj {
     Rboolean R_HasFancyBindings(SEXP rho);
     */
 
-    /// Read-only access to attribute list.
-    // fn attrib(&self) -> Robj {
-    // unsafe {new_borrowed(ATTRIB(self.get()))}
-    // }
+    /// Iterate over all the attributes of an object as (name, value) pairs.
+    /// ```
+    /// use extendr_api::*;
+    /// start_r();
+    /// let x = Robj::eval_string("structure(1, a = 2)").unwrap();
+    /// let names: Vec<&str> = x.attributes().map(|(name, _)| name).collect();
+    /// assert_eq!(names, vec!["a"]);
+    /// ```
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, Robj)> {
+        let mut attrib = unsafe { ATTRIB(self.get()) };
+        std::iter::from_fn(move || unsafe {
+            if attrib == R_NilValue {
+                None
+            } else {
+                let name = to_str(R_CHAR(PRINTNAME(TAG(attrib))) as *const u8);
+                let value = new_borrowed(CAR(attrib));
+                attrib = CDR(attrib);
+                Some((name, value))
+            }
+        })
+    }
 
     /// Copy a vector and resize it.
     /// See. https://github.com/hadley/r-internals/blob/master/vectors.md
@@ -957,7 +1816,14 @@ impl Robj {
         unsafe { Robj::from(Rf_elt(self.get(), index as raw::c_int)) }
     }
 
-    //Rboolean Rf_inherits(SEXP, const char *);
+    /// Return true if the class attribute contains classname.
+    pub fn inherits(&self, classname: &str) -> bool {
+        if let Ok(classname) = std::ffi::CString::new(classname) {
+            unsafe { Rf_inherits(self.get(), classname.as_ptr()) != 0 }
+        } else {
+            false
+        }
+    }
 
     /// Return true if this is an array.
     pub fn isArray(&self) -> bool {
@@ -1075,6 +1941,21 @@ impl Robj {
         }
         false
     }
+
+    /// Move the value out of an external pointer made from a `Box<T>`,
+    /// such as those made by #[extendr] impl.
+    /// The pointer is cleared so that the finalizer does not drop the value again.
+    /// Returns None if the pointer has already been cleared.
+    /// This is unsafe because the type of the value is not checked.
+    pub unsafe fn take_external_ptr<T>(&self) -> Option<T> {
+        let ptr = self.externalPtrAddr::<T>();
+        if ptr.is_null() {
+            None
+        } else {
+            self.clearExternalPtr();
+            Some(*Box::from_raw(ptr))
+        }
+    }
 }
 
 pub unsafe fn new_owned(sexp: SEXP) -> Robj {
@@ -1139,7 +2020,12 @@ impl PartialEq<Robj> for Robj {
                     VECSXP | EXPRSXP => self.list_iter().unwrap().eq(rhs.list_iter().unwrap()),
                     STRSXP => self.str_iter().unwrap().eq(rhs.str_iter().unwrap()),
                     BCODESXP => false,
-                    EXTPTRSXP => false,
+                    // External pointers are equal if they point to the same value.
+                    // Cleared (null) pointers are only equal to themselves.
+                    EXTPTRSXP => {
+                        let addr = R_ExternalPtrAddr(lsexp);
+                        lsexp == rsexp || (!addr.is_null() && addr == R_ExternalPtrAddr(rsexp))
+                    }
                     WEAKREFSXP => false,
                     RAWSXP => self.as_u8_slice() == rhs.as_u8_slice(),
                     S4SXP => false,
@@ -1337,6 +2223,39 @@ impl From<usize> for Robj {
     }
 }
 
+/// Convert a UUID to a character scalar in the hyphenated form.
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Robj {
+    fn from(val: uuid::Uuid) -> Self {
+        Robj::from(val.to_hyphenated().to_string().as_str())
+    }
+}
+
+/// Convert a complex number to a complex scalar.
+#[cfg(feature = "num-complex")]
+impl From<num_complex::Complex<f64>> for Robj {
+    fn from(val: num_complex::Complex<f64>) -> Self {
+        Robj::from(&[val][..])
+    }
+}
+
+/// Convert a slice of complex numbers to a complex vector.
+#[cfg(feature = "num-complex")]
+impl<'a> From<&'a [num_complex::Complex<f64>]> for Robj {
+    fn from(vals: &[num_complex::Complex<f64>]) -> Self {
+        unsafe {
+            let sexp = Rf_allocVector(CPLXSXP, vals.len() as R_xlen_t);
+            R_PreserveObject(sexp);
+            let slice = std::slice::from_raw_parts_mut(COMPLEX(sexp), vals.len());
+            for (c, v) in slice.iter_mut().zip(vals.iter()) {
+                c.r = v.re;
+                c.i = v.im;
+            }
+            Robj::Owned(sexp)
+        }
+    }
+}
+
 /// Convert a wrapped string ref to an Robj char object.
 impl<'a> From<Character<'a>> for Robj {
     fn from(val: Character) -> Self {
@@ -1473,10 +2392,14 @@ impl<T: AsRef<str>> From<Vec<T>> for Robj {
             // note: a better way would be to steal the allocated buffer from the strings,
             for (i, s) in vals.iter().enumerate() {
                 // note that SET_STRING_ELT is more than a store.
-                SET_STRING_ELT(sexp, i as R_xlen_t, Rf_mkCharLen(
-                    s.as_ref().as_ptr() as *const raw::c_char,
-                    s.as_ref().len() as i32,
-                ));
+                SET_STRING_ELT(
+                    sexp,
+                    i as R_xlen_t,
+                    Rf_mkCharLen(
+                        s.as_ref().as_ptr() as *const raw::c_char,
+                        s.as_ref().len() as i32,
+                    ),
+                );
             }
 
             // The sexp is already protected but we need to unprotect it when it dies.
@@ -1545,32 +2468,169 @@ pub struct StrIter {
     len: usize,
 }
 
+impl StrIter {
+    fn str_elt(&self, i: usize) -> &'static str {
+        unsafe {
+            let sexp = STRING_ELT(self.vector, i as isize);
+            let ptr = R_CHAR(sexp) as *const u8;
+            let slice = std::slice::from_raw_parts(ptr, Rf_xlength(sexp) as usize);
+            std::str::from_utf8_unchecked(slice)
+        }
+    }
+
+    /// Iterate over the strings, giving None for NA elements.
+    pub fn options(self) -> impl Iterator<Item = Option<&'static str>> {
+        // NA strings all share the storage of R_NaString.
+        let na = unsafe { R_CHAR(R_NaString) as *const u8 };
+        self.map(move |s| if s.as_ptr() == na { None } else { Some(s) })
+    }
+
+    /// Collect the remaining strings into a character vector.
+    /// This reuses the existing CHARSXP elements, so NA is preserved and nothing is re-interned.
+    pub fn collect_strings(self) -> Robj {
+        unsafe {
+            let res = new_owned(Rf_allocVector(STRSXP, (self.len - self.i) as R_xlen_t));
+            for (j, i) in (self.i..self.len).enumerate() {
+                SET_STRING_ELT(
+                    res.get(),
+                    j as R_xlen_t,
+                    STRING_ELT(self.vector, i as R_xlen_t),
+                );
+            }
+            res
+        }
+    }
+}
+
 impl Iterator for StrIter {
     type Item = &'static str;
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.len, Some(self.len))
+        (self.len - self.i, Some(self.len - self.i))
     }
 
     fn next(&mut self) -> Option<Self::Item> {
         let i = self.i;
-        self.i += 1;
         if i >= self.len {
             return None;
         } else {
-            unsafe {
-                let sexp = STRING_ELT(self.vector, i as isize);
-                let ptr = R_CHAR(sexp) as *const u8;
-                let slice = std::slice::from_raw_parts(ptr, Rf_xlength(sexp) as usize);
-                Some(std::str::from_utf8_unchecked(slice))
-            }
+            self.i += 1;
+            Some(self.str_elt(i))
         }
     }
 
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.i += n;
+        // Keep i <= len so that the remaining length never underflows.
+        self.i = std::cmp::min(self.i.saturating_add(n), self.len);
         self.next()
     }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl ExactSizeIterator for StrIter {}
+
+impl DoubleEndedIterator for StrIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.i >= self.len {
+            None
+        } else {
+            self.len -= 1;
+            Some(self.str_elt(self.len))
+        }
+    }
+}
+
+/// Build a character vector from a stream of strings.
+/// The strings are written into one preserved STRSXP as they are pushed, which grows
+/// as needed. R's CHARSXP cache already makes repeated strings share one CHARSXP.
+/// ```
+/// use extendr_api::*;
+/// start_r();
+/// let mut builder = StringsBuilder::new();
+/// for s in &["a", "b", "a"] {
+///     builder.push(s);
+/// }
+/// builder.push_na();
+/// let strings = builder.build();
+/// assert_eq!(strings.len(), 4);
+/// ```
+pub struct StringsBuilder {
+    vector: Robj,
+    len: usize,
+}
+
+impl StringsBuilder {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Make a builder with room for capacity strings before it needs to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            vector: unsafe { new_owned(Rf_allocVector(STRSXP, capacity as R_xlen_t)) },
+            len: 0,
+        }
+    }
+
+    // Make room for one more string. This allocates, so call it before making the CHARSXP.
+    fn reserve_one(&mut self) {
+        let capacity = self.vector.len();
+        if self.len == capacity {
+            unsafe {
+                let grown = new_owned(Rf_allocVector(STRSXP, (capacity * 2).max(8) as R_xlen_t));
+                for i in 0..self.len as R_xlen_t {
+                    SET_STRING_ELT(grown.get(), i, STRING_ELT(self.vector.get(), i));
+                }
+                self.vector = grown;
+            }
+        }
+    }
+
+    /// Append a string.
+    pub fn push(&mut self, s: &str) {
+        self.reserve_one();
+        unsafe {
+            let charsxp = Rf_mkCharLen(s.as_ptr() as *const raw::c_char, s.len() as i32);
+            SET_STRING_ELT(self.vector.get(), self.len as R_xlen_t, charsxp);
+        }
+        self.len += 1;
+    }
+
+    /// Append an NA string.
+    pub fn push_na(&mut self) {
+        self.reserve_one();
+        unsafe {
+            SET_STRING_ELT(self.vector.get(), self.len as R_xlen_t, R_NaString);
+        }
+        self.len += 1;
+    }
+
+    /// The number of strings pushed so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Make the character vector, trimming any unused capacity.
+    pub fn build(self) -> Robj {
+        if self.len == self.vector.len() {
+            self.vector
+        } else {
+            self.vector.xlengthgets(self.len).unwrap()
+        }
+    }
+}
+
+impl Default for StringsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -1619,6 +2679,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_type_name() {
+        start_r();
+        assert_eq!(Robj::from(()).type_name(), "NULL");
+        assert_eq!(Robj::from(true).type_name(), "logical");
+        assert_eq!(Robj::from(1).type_name(), "integer");
+        assert_eq!(Robj::from(1.).type_name(), "double");
+        assert_eq!(Robj::from("a").type_name(), "character");
+        assert_eq!(Robj::eval_string("list()").unwrap().type_name(), "list");
+        assert_eq!(Robj::globalEnv().type_name(), "environment");
+        let err = Robj::from("a").try_into_vec_reporting::<i32>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer or double vector, got character"
+        );
+    }
+
     #[test]
     fn test_from_robj() {
         assert_eq!(<u8>::from_robj(&Robj::from(1)), Ok(1));
@@ -1633,6 +2710,18 @@ mod tests {
         assert_eq!(<f64>::from_robj(&Robj::from(1)), Ok(1.));
         assert_eq!(<Vec::<i32>>::from_robj(&Robj::from(1)), Ok(vec![1]));
         assert_eq!(<Vec::<f64>>::from_robj(&Robj::from(1.)), Ok(vec![1.]));
+        assert_eq!(
+            <Vec::<i32>>::from_robj(&Robj::from("a")),
+            Err("expected integer, got character")
+        );
+        assert_eq!(
+            <Vec::<f64>>::from_robj(&Robj::from(())),
+            Err("expected double, got NULL")
+        );
+        assert_eq!(
+            <&[u8]>::from_robj(&Robj::from(true)),
+            Err("expected raw, got logical")
+        );
         assert_eq!(
             <ArrayView1<f64>>::from_robj(&Robj::from(1.)),
             Ok(ArrayView1::<f64>::from(&[1.][..]))
@@ -1645,6 +2734,14 @@ mod tests {
             <ArrayView1<Bool>>::from_robj(&Robj::from(true)),
             Ok(ArrayView1::<Bool>::from(&[Bool(1)][..]))
         );
+        assert_eq!(
+            <ArrayView1<f64>>::from_robj(&Robj::from(1)),
+            Err("expected double, got integer")
+        );
+        assert_eq!(
+            <&[i32]>::from_robj(&Robj::from(1.)),
+            Err("expected integer, got double")
+        );
         assert_eq!(
             <ArrayView2<f64>>::from_robj(&Robj::from(1.)),
             Ok(ArrayView2::<f64>::from_shape((1, 1), &[1.][..]).unwrap())
@@ -1672,6 +2769,70 @@ mod tests {
 
         let hello = Robj::from("hello");
         assert_eq!(<&str>::from_robj(&hello), Ok("hello"));
+        assert_eq!(<bool>::from_robj(&Robj::from(true)), Ok(true));
+        assert_eq!(<char>::from_robj(&Robj::from("x")), Ok('x'));
+        assert_eq!(<char>::from_robj(&Robj::from("é")), Ok('é'));
+        assert_eq!(
+            <char>::from_robj(&Robj::from("xy")),
+            Err("expected a single character")
+        );
+        assert_eq!(
+            <char>::from_robj(&Robj::from("")),
+            Err("expected a single character")
+        );
+        assert_eq!(
+            <char>::from_robj(&Robj::from(1)),
+            Err("not a string object")
+        );
+        assert_eq!(<&[f64]>::from_robj(&Robj::from(1.)), Ok(&[1.][..]));
+        assert!(<&[f64]>::from_robj(&Robj::from(1)).is_err());
+        assert_eq!(<Option<&[i32]>>::from_robj(&Robj::from(())), Ok(None));
+        assert_eq!(
+            <Option<&[i32]>>::from_robj(&Robj::from(1)),
+            Ok(Some(&[1][..]))
+        );
+        assert_eq!(<Option<i32>>::from_robj(&Robj::from(())), Ok(None));
+    }
+
+    #[test]
+    fn test_from_robj_empty_and_na() {
+        start_r();
+        let empty = Robj::eval_string("logical(0)").unwrap();
+        assert_eq!(<bool>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<&str>::from_robj(&empty), Err("not a string object"));
+        assert_eq!(<i32>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<u8>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<i64>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<f64>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<f32>::from_robj(&empty), Err("zero length vector"));
+        let empty = Robj::eval_string("integer(0)").unwrap();
+        assert_eq!(<i32>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<u8>::from_robj(&empty), Err("zero length vector"));
+        let empty = Robj::eval_string("numeric(0)").unwrap();
+        assert_eq!(<f64>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<i64>::from_robj(&empty), Err("zero length vector"));
+
+        let empty = Robj::eval_string("character(0)").unwrap();
+        assert_eq!(<&str>::from_robj(&empty), Err("zero length vector"));
+        assert_eq!(<String>::from_robj(&empty), Err("zero length vector"));
+
+        let na = Robj::eval_string("NA").unwrap();
+        assert_eq!(<bool>::from_robj(&na), Err("must not be NA"));
+        assert_eq!(
+            <i32>::from_robj(&na),
+            Err("unable to convert R object to primitive")
+        );
+        let na = Robj::eval_string("NA_integer_").unwrap();
+        assert_eq!(<i32>::from_robj(&na), Err("must not be NA"));
+        assert_eq!(<u8>::from_robj(&na), Err("must not be NA"));
+        assert!(<f64>::from_robj(&na).unwrap().is_nan());
+        assert!(<f32>::from_robj(&na).unwrap().is_nan());
+        let na = Robj::eval_string("NA_real_").unwrap();
+        assert_eq!(<i32>::from_robj(&na), Err("must not be NA"));
+        assert_eq!(<u64>::from_robj(&na), Err("must not be NA"));
+        assert!(<f64>::from_robj(&na).unwrap().is_nan());
+        let na = Robj::eval_string("NA_character_").unwrap();
+        assert_eq!(<&str>::from_robj(&na), Err("must not be NA"));
     }
     #[test]
     fn test_to_robj() {
@@ -1706,4 +2867,498 @@ mod tests {
         assert_eq!(p, Robj::from(2));
         Ok(())
     }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid() {
+        start_r();
+        let text = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let uuid = uuid::Uuid::parse_str(text).unwrap();
+        assert_eq!(Robj::from(uuid), Robj::from(text));
+        assert_eq!(<uuid::Uuid>::from_robj(&Robj::from(text)), Ok(uuid));
+        assert!(<uuid::Uuid>::from_robj(&Robj::from("not-a-uuid")).is_err());
+        assert!(<uuid::Uuid>::from_robj(&Robj::from(1)).is_err());
+        let na = Robj::eval_string("NA_character_").unwrap();
+        assert_eq!(<uuid::Uuid>::from_robj(&na), Err("NA is not a valid UUID"));
+    }
+
+    #[cfg(feature = "num-complex")]
+    #[test]
+    fn test_num_complex() -> Result<(), AnyError> {
+        use num_complex::Complex;
+        start_r();
+        let z = Robj::eval_string("c(1+2i, -3i, NA)")?;
+        let v = <Vec<Complex<f64>>>::from_robj(&z)?;
+        assert_eq!(&v[..2], &[Complex::new(1.0, 2.0), Complex::new(0.0, -3.0)]);
+        assert!(v[2].re.is_nan());
+        assert_eq!(
+            <Complex<f64>>::from_robj(&Robj::eval_string("0.5+1i")?),
+            Ok(Complex::new(0.5, 1.0))
+        );
+        assert!(<Complex<f64>>::from_robj(&z).is_err());
+        assert!(<Vec<Complex<f64>>>::from_robj(&Robj::from(1.0)).is_err());
+
+        let back = Robj::from(&v[..2]);
+        assert_eq!(back.sexptype(), CPLXSXP);
+        assert_eq!(<Vec<Complex<f64>>>::from_robj(&back)?, &v[..2]);
+        let scalar = Robj::from(Complex::new(1.0, -1.0));
+        assert_eq!(
+            <Complex<f64>>::from_robj(&scalar),
+            Ok(Complex::new(1.0, -1.0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        start_r();
+        let raw = Robj::eval_string("as.raw(c(1, 2, 255))").unwrap();
+        assert_eq!(raw.as_raw_bytes(), Some(&[1, 2, 255][..]));
+        assert_eq!(Robj::from(1).as_raw_bytes(), None);
+
+        let serialized = Robj::eval_string("serialize(1L, NULL)").unwrap();
+        assert!(serialized.as_raw_bytes().unwrap().len() > 0);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes() {
+        start_r();
+        let raw = Robj::from(&[1u8, 2, 3][..]);
+        assert_eq!(
+            <bytes::Bytes>::from_robj(&raw),
+            Ok(bytes::Bytes::from(&[1u8, 2, 3][..]))
+        );
+        assert!(<bytes::Bytes>::from_robj(&Robj::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_external_ptr_protected() {
+        start_r();
+        let vec = Robj::from(&[1, 2, 3][..]);
+        unsafe {
+            let ptr = Robj::makeExternalPtr(
+                std::ptr::null_mut::<i32>(),
+                Robj::from("tag"),
+                Robj::from(()),
+            );
+            assert_eq!(ptr.externalPtrProtected(), Robj::from(()));
+            ptr.setExternalPtrProtected(Robj::from(vec.get()));
+            assert_eq!(ptr.externalPtrProtected(), vec);
+
+            let mut value = 1;
+            let a = Robj::makeExternalPtr(&mut value as *mut i32, Robj::from("a"), Robj::from(()));
+            let b = Robj::makeExternalPtr(&mut value as *mut i32, Robj::from("b"), Robj::from(()));
+            assert_eq!(a, b);
+            assert_eq!(a, new_borrowed(a.get()));
+            assert_ne!(a, ptr);
+            assert_eq!(ptr, new_borrowed(ptr.get()));
+            let c =
+                Robj::makeExternalPtr(Box::into_raw(Box::new(1)), Robj::from("c"), Robj::from(()));
+            let d =
+                Robj::makeExternalPtr(Box::into_raw(Box::new(2)), Robj::from("d"), Robj::from(()));
+            assert_eq!(c.take_external_ptr::<i32>(), Some(1));
+            assert_eq!(d.take_external_ptr::<i32>(), Some(2));
+            assert_ne!(c, d);
+            assert_ne!(c, ptr);
+            assert_eq!(c, new_borrowed(c.get()));
+
+            let ptr = ptr.with_class("tag");
+            assert!(ptr.inherits("tag"));
+            assert!(ptr.check_external_ptr("tag"));
+        }
+    }
+
+    #[test]
+    fn test_statistics() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::from(&[1., 2., 3.][..]);
+        let w = Robj::from(&[3., 2., 1.][..]);
+        assert_eq!(x.weighted_mean(&w, false)?, 10. / 6.);
+        assert_eq!(x.weighted_mean(&Robj::from(&[1, 1, 1][..]), false)?, 2.);
+        assert!(x.weighted_mean(&Robj::from(&[1., 1.][..]), false).is_err());
+
+        let x = Robj::eval_string("c(1, NA, 3)")?;
+        let w = Robj::from(&[1., 1., 2.][..]);
+        assert_eq!(x.weighted_mean(&w, true)?, 7. / 3.);
+        assert!(x.weighted_mean(&w, false)?.is_nan());
+
+        let m = Robj::eval_string("matrix(c(1, 2, 3, 2, 4, 6), ncol=2)")?;
+        let cov = m.cov()?;
+        assert_eq!((cov.nrows(), cov.ncols()), (2, 2));
+        assert_eq!(cov.as_f64_slice(), Some(&[1., 2., 2., 4.][..]));
+
+        let cor = m.cor()?;
+        assert_eq!((cor.nrows(), cor.ncols()), (2, 2));
+        for &r in cor.as_f64_slice().unwrap() {
+            assert!((r - 1.).abs() < 1e-10);
+        }
+
+        assert!(x.cor().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompositions() -> Result<(), AnyError> {
+        start_r();
+        let m = Robj::eval_string("matrix(c(4, 2, 2, 3), ncol=2)")?;
+        let qr = m.qr()?;
+        assert_eq!(qr.list_iter().unwrap().nth(1), Some(Robj::from(2)));
+
+        let chol = m.chol()?;
+        let r = chol.as_f64_slice().unwrap();
+        assert_eq!((r[0], r[1], r[2]), (2., 0., 1.));
+        assert!((r[3] - 2f64.sqrt()).abs() < 1e-10);
+
+        let eigen = m.eigen()?;
+        let values = eigen.list_iter().unwrap().next().unwrap();
+        let values = values.as_f64_slice().unwrap();
+        assert!((values[0] - 5.5615528128).abs() < 1e-8);
+        assert!((values[1] - 1.4384471872).abs() < 1e-8);
+
+        let not_pd = Robj::eval_string("matrix(c(1, 2, 2, 1), ncol=2)")?;
+        assert!(not_pd.chol().is_err());
+        let not_square = Robj::eval_string("matrix(1:6 + 0, ncol=2)")?;
+        assert!(not_square.chol().is_err());
+        assert!(not_square.eigen().is_err());
+        assert!(not_square.qr().is_ok());
+        assert!(Robj::from(1.).qr().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_det_inverse() -> Result<(), AnyError> {
+        start_r();
+        let m = Robj::eval_string("matrix(c(4, 2, 7, 6), ncol=2)")?;
+        assert!((m.det()? - 10.).abs() < 1e-10);
+        let inv = m.inverse()?;
+        assert_eq!((inv.nrows(), inv.ncols()), (2, 2));
+        let expected = [0.6, -0.2, -0.7, 0.4];
+        for (x, y) in inv.as_f64_slice().unwrap().iter().zip(expected.iter()) {
+            assert!((x - y).abs() < 1e-10);
+        }
+
+        let singular = Robj::eval_string("matrix(c(1, 2, 2, 4), ncol=2)")?;
+        assert!(singular.inverse().is_err());
+        let not_square = Robj::eval_string("matrix(1:6 + 0, ncol=2)")?;
+        assert!(not_square.det().is_err());
+        assert!(not_square.inverse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleave() -> Result<(), AnyError> {
+        start_r();
+        let a = Robj::eval_string("c(1, 3, 5)")?;
+        let b = Robj::eval_string("c(2, 4, 6)")?;
+        let c = Robj::interleave(&[&a, &b])?;
+        assert_eq!(c.as_f64_slice(), Some(&[1., 2., 3., 4., 5., 6.][..]));
+
+        let a = Robj::from(vec!["a", "c"]);
+        let b = Robj::from(vec!["b", "d"]);
+        let c = Robj::interleave(&[&a, &b])?;
+        assert_eq!(c, Robj::from(vec!["a", "b", "c", "d"]));
+
+        // Robj equality does not compare complex vectors, so use identical().
+        let z = Robj::interleave(&[
+            &Robj::eval_string("c(1+2i, 3+4i)")?,
+            &Robj::eval_string("c(5+6i, 7+8i)")?,
+        ])?;
+        let expected = Robj::eval_string("c(1+2i, 5+6i, 3+4i, 7+8i)")?;
+        let (z, expected) = unsafe { (z.get(), expected.get()) };
+        assert_eq!(lang!("identical", z, expected).eval()?, Robj::from(true));
+
+        assert!(Robj::interleave(&[&a, &Robj::from(vec!["b"])]).is_err());
+        assert!(Robj::interleave(&[&a, &Robj::from(&[1, 2][..])]).is_err());
+        assert!(Robj::interleave(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_into_vec_reporting() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("c(1L, 2L, 3L)")?;
+        assert_eq!(x.try_into_vec_reporting::<u8>()?, vec![1, 2, 3]);
+        assert_eq!(x.try_into_vec_reporting::<f64>()?, vec![1., 2., 3.]);
+
+        let x = Robj::eval_string("c(1L, 2L, NA, 4L)")?;
+        let err = x.try_into_vec_reporting::<i32>().unwrap_err();
+        assert_eq!(err.to_string(), "element 3 is NA");
+
+        let x = Robj::eval_string("c(1, 2, 3, 4, 300)")?;
+        let err = x.try_into_vec_reporting::<u8>().unwrap_err();
+        assert_eq!(err.to_string(), "element 5 (300) is out of range");
+        assert_eq!(x.try_into_vec_reporting::<u16>()?, vec![1, 2, 3, 4, 300]);
+
+        assert!(Robj::eval_string("c(1.5)")?
+            .try_into_vec_reporting::<i32>()
+            .is_err());
+        assert!(Robj::from("a").try_into_vec_reporting::<i32>().is_err());
+
+        assert_eq!(u8::from_f64(255.), Some(255));
+        assert_eq!(u8::from_f64(256.), None);
+        assert_eq!(u64::from_f64(2f64.powi(64)), None);
+        assert_eq!(i64::from_f64(2f64.powi(63)), None);
+        assert_eq!(i64::from_f64(-(2f64.powi(63))), Some(i64::MIN));
+        Ok(())
+    }
+
+    #[test]
+    fn test_str_iter_rev() {
+        start_r();
+        let x = Robj::from(vec!["a", "b", "c"]);
+        let v: Vec<_> = x.str_iter().unwrap().rev().collect();
+        assert_eq!(v, ["c", "b", "a"]);
+        assert_eq!(x.str_iter().unwrap().last(), Some("c"));
+
+        let mut iter = x.str_iter().unwrap();
+        assert_eq!(iter.next(), Some("a"));
+        assert_eq!(iter.next_back(), Some("c"));
+        assert_eq!(iter.next_back(), Some("b"));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn test_str_iter_len() {
+        start_r();
+        let x = Robj::from(vec!["a", "b", "c"]);
+        let mut iter = x.str_iter().unwrap();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.nth(1), Some("b"));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.nth(1000), None);
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.len(), 0);
+    }
+
+    #[test]
+    fn test_str_iter_options() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("c(\"a\", NA, \"NA\")")?;
+        let v: Vec<_> = x.str_iter().unwrap().options().collect();
+        assert_eq!(v, [Some("a"), None, Some("NA")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_strings() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("c(\"a\", NA, \"c\")")?;
+        assert_eq!(x.str_iter().unwrap().collect_strings(), x);
+
+        let mut iter = x.str_iter().unwrap();
+        iter.next();
+        let y = iter.collect_strings();
+        assert_eq!(y, Robj::eval_string("c(NA, \"c\")")?);
+        let v: Vec<_> = y.str_iter().unwrap().options().collect();
+        assert_eq!(v, [None, Some("c")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zeros_matrix() {
+        start_r();
+        let m = Robj::zeros_matrix(REALSXP, 2, 3);
+        assert_eq!((m.nrows(), m.ncols()), (2, 3));
+        assert_eq!(m.as_f64_slice(), Some(&[0.; 6][..]));
+        let m = Robj::zeros_matrix(INTSXP, 3, 1);
+        assert_eq!(m.as_i32_slice(), Some(&[0; 3][..]));
+    }
+
+    #[test]
+    fn test_new_factor() -> Result<(), AnyError> {
+        start_r();
+        let na = unsafe { R_NaInt };
+        let f = Robj::new_factor(&[2, 1, na, 2], &["a", "b"])?;
+        assert!(f.inherits("factor"));
+        let expected =
+            Robj::eval_string("factor(c(\"b\", \"a\", NA, \"b\"), levels = c(\"a\", \"b\"))")?;
+        let (f, expected) = unsafe { (f.get(), expected.get()) };
+        let identical = lang!("identical", f, expected).eval()?;
+        assert_eq!(identical, Robj::from(true));
+
+        assert!(Robj::new_factor(&[0], &["a"]).is_err());
+        assert!(Robj::new_factor(&[3], &["a", "b"]).is_err());
+        assert!(Robj::new_factor(&[1], &["a", "a"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_from_iter() {
+        start_r();
+        let list = Robj::list_from_iter(vec![Robj::from(1), Robj::from("x")]);
+        assert_eq!(list, List(&[Robj::from(1), Robj::from("x")]));
+        assert_eq!(list.getAttrib(&Robj::namesSymbol()), Robj::from(()));
+
+        let named = Robj::named_list_from_iter((1..=2).map(|i| (format!("a{}", i), Robj::from(i))));
+        assert_eq!(named, List(&[Robj::from(1), Robj::from(2)]));
+        assert_eq!(
+            named.getAttrib(&Robj::namesSymbol()),
+            Robj::from(&["a1", "a2"][..])
+        );
+        assert_eq!(Robj::list_from_iter(std::iter::empty()).len(), 0);
+
+        // An iterator without a size hint makes the list grow.
+        let long = Robj::list_from_iter((0..20).filter(|_| true).map(Robj::from));
+        assert_eq!(long.len(), 20);
+        assert_eq!(long.list_iter().unwrap().nth(19), Some(Robj::from(19)));
+    }
+
+    #[test]
+    fn test_to_factor() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("c(\"z\", NA, \"a\", \"z\")")?;
+        let f = x.to_factor()?;
+        let expected =
+            Robj::eval_string("factor(c(\"z\", NA, \"a\", \"z\"), levels = c(\"z\", \"a\"))")?;
+        assert_eq!(f, expected);
+        assert_eq!(
+            f.getAttrib(&Robj::levelsSymbol()),
+            Robj::from(&["z", "a"][..])
+        );
+        assert!(f.inherits("factor"));
+        assert!(Robj::from(1).to_factor().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_factor_lookup() -> Result<(), AnyError> {
+        start_r();
+        let f = Robj::eval_string("factor(c(\"b\", NA, \"a\"))")?;
+        assert_eq!(f.factor_code_of(0), Some(2));
+        assert_eq!(f.factor_level_of(0), Some("b"));
+        assert_eq!(f.factor_code_of(1), None);
+        assert_eq!(f.factor_level_of(1), None);
+        assert_eq!(f.factor_level_of(2), Some("a"));
+        assert_eq!(f.factor_level_of(3), None);
+        assert_eq!(Robj::from(1).factor_code_of(0), None);
+        let bad = Robj::eval_string("structure(0L, levels = \"a\", class = \"factor\")")?;
+        assert_eq!(bad.factor_level_of(0), None);
+
+        let pairs: Vec<_> = f.factor_iter().unwrap().collect();
+        assert_eq!(pairs, vec![(2, "b"), (i32::MIN, "NA"), (1, "a")]);
+        assert!(Robj::from(1).factor_iter().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_as_character() -> Result<(), AnyError> {
+        start_r();
+        let check = |expr: &str| -> Result<(), AnyError> {
+            let x = Robj::eval_string(expr)?;
+            let expected = Robj::eval_string(&format!("as.character({})", expr))?;
+            assert_eq!(x.as_character()?, expected, "{}", expr);
+            Ok(())
+        };
+        check("c(TRUE, FALSE, NA)")?;
+        check("c(1L, -20L, NA)")?;
+        check("c(1, 1.5, -0.25, 123456, 1e5, 1e-4, 1/3, 1e15, NA, NaN, Inf, -Inf)")?;
+        check("factor(c(\"b\", NA, \"a\"))")?;
+        check("c(\"x\", NA)")?;
+        assert!(Robj::from(()).as_character().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() -> Result<(), AnyError> {
+        start_r();
+        assert_eq!(Robj::from("a").format()?, "[1] \"a\"");
+        assert_eq!(Robj::from(Symbol("not_bound")).format()?, "not_bound");
+        let call = Robj::eval_string("quote(stop(\"evaluated\"))")?;
+        assert_eq!(call.format()?, "stop(\"evaluated\")");
+        let m = Robj::eval_string("matrix(1:4, 2)")?;
+        assert_eq!(
+            m.format()?,
+            "     [,1] [,2]\n[1,]    1    3\n[2,]    2    4"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_strings_builder() {
+        start_r();
+        let mut builder = StringsBuilder::new();
+        assert!(builder.is_empty());
+        for i in 0..1000 {
+            builder.push(["x", "yy", "zzz"][i % 3]);
+        }
+        builder.push_na();
+        assert_eq!(builder.len(), 1001);
+        let strings = builder.build();
+        assert_eq!(strings.sexptype(), STRSXP);
+        assert_eq!(strings.len(), 1001);
+        let mut iter = strings.str_iter().unwrap();
+        assert_eq!(iter.nth(0), Some("x"));
+        assert_eq!(iter.nth(0), Some("yy"));
+        assert_eq!(iter.nth(997), Some("x"));
+        assert_eq!(strings.str_iter().unwrap().options().last(), Some(None));
+
+        let mut builder = StringsBuilder::with_capacity(2);
+        builder.push("a");
+        builder.push("b");
+        assert_eq!(builder.build(), Robj::from(&["a", "b"][..]));
+        assert_eq!(StringsBuilder::new().build().len(), 0);
+    }
+
+    #[test]
+    fn test_try_as_typed_slice() {
+        start_r();
+        let x = Robj::from(&[1, 2][..]);
+        assert_eq!(x.try_as_typed_slice::<i32>().unwrap(), &[1, 2]);
+        let err = x.try_as_typed_slice::<f64>().unwrap_err();
+        assert_eq!(err.to_string(), "expected double, got integer");
+        let err = Robj::from("a").try_as_typed_slice::<u8>().unwrap_err();
+        assert_eq!(err.to_string(), "expected raw, got character");
+    }
+
+    #[test]
+    fn test_attributes() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("structure(1:4, dim = c(2L, 2L), class = \"foo\")")?;
+        let attrs: Vec<_> = x.attributes().collect();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs[0].0, "dim");
+        assert_eq!(attrs[0].1, Robj::from(&[2, 2][..]));
+        assert_eq!(attrs[1].0, "class");
+        assert_eq!(attrs[1].1, Robj::from("foo"));
+        assert_eq!(Robj::from(1).attributes().count(), 0);
+
+        let mut y = Robj::eval_string(
+            "structure(c(a = 1, b = 2, c = 3, d = 4), dim = c(2L, 2L), class = \"foo\")",
+        )?;
+        y.clear_attributes();
+        assert_eq!(y.attributes().count(), 0);
+        assert!(!y.inherits("foo"));
+        assert_eq!(y.getAttrib(&Robj::dimSymbol()), Robj::from(()));
+        assert_eq!(y.getAttrib(&Robj::namesSymbol()), Robj::from(()));
+        assert_eq!(y, Robj::from(&[1.0, 2.0, 3.0, 4.0][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_as_numeric_parsed() -> Result<(), AnyError> {
+        start_r();
+        let x = Robj::eval_string("c(\"1\", \"2.5\", \"x\")")?;
+        let y = x.as_numeric_parsed(false)?;
+        let y = y.as_f64_slice().unwrap();
+        assert_eq!(&y[..2], &[1., 2.5]);
+        assert!(y[2].is_nan());
+        assert!(x.as_numeric_parsed(true).is_err());
+
+        let x = Robj::eval_string("c(\" 3 \", NA, \"-Inf\")")?;
+        let y = x.as_numeric_parsed(true)?;
+        let y = y.as_f64_slice().unwrap();
+        assert_eq!((y[0], y[2]), (3., std::f64::NEG_INFINITY));
+        assert!(y[1].is_nan());
+
+        let x = Robj::eval_string("c(\"\", \"  \", \"+Inf\")")?;
+        let y = x.as_numeric_parsed(true)?;
+        let y = y.as_f64_slice().unwrap();
+        assert!(y[0].is_nan() && y[1].is_nan());
+        assert_eq!(y[2], std::f64::INFINITY);
+        assert!(Robj::from(1.).as_numeric_parsed(false).is_err());
+        Ok(())
+    }
 }
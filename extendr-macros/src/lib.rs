@@ -9,8 +9,16 @@ const INIT_PREFIX : &str = "init__";
 const WRAP_PREFIX : &str = "wrap__";
 
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ExtendrOptions {
+    // Generate a print wrapper using the Debug implementation, eg. wrap__Foo__print.
+    r_print_debug: bool,
+    // Generate wrap__Foo__length calling `fn len(&self) -> usize`.
+    r_length: bool,
+    // Generate wrap__Foo__index calling `fn get(&self, i: usize) -> Option<Robj>` with a zero based index.
+    r_index: bool,
+    // Generate accessors for the pub fields of a struct, eg. wrap__Foo__x.
+    expose_fields: bool,
 }
 
 // Generate a list of arguments for the wrapper. All arguments are SEXP for .Call in R.
@@ -21,10 +29,10 @@ fn translate_formal(input: &FnArg, self_ty: Option<&syn::Type>) -> FnArg {
             let pat = &pattype.pat.as_ref();
             return parse_quote! { #pat : extendr_api::SEXP };
         }
-        // &self
+        // &self, &mut self or self
         FnArg::Receiver(ref reciever) => {
-            if !reciever.attrs.is_empty() || reciever.reference.is_none() {
-                panic!("expected &self or &mut self");
+            if !reciever.attrs.is_empty() {
+                panic!("expected &self, &mut self or self");
             }
             if self_ty.is_none() {
                 panic!("found &self in non-impl function - have you missed the #[extendr] before the impl?");
@@ -34,6 +42,19 @@ fn translate_formal(input: &FnArg, self_ty: Option<&syn::Type>) -> FnArg {
     }
 }
 
+// Get T from an output parameter type &mut [T].
+fn mut_slice_elem(ty: &syn::Type) -> Option<&syn::Type> {
+    match ty {
+        syn::Type::Reference(ref reference) if reference.mutability.is_some() => {
+            match reference.elem.as_ref() {
+                syn::Type::Slice(ref slice) => Some(slice.elem.as_ref()),
+                _ => None
+            }
+        }
+        _ => None
+    }
+}
+
 // Convert SEXP arguments into Robj. This maintains the lifetime of references.
 fn translate_to_robj(input: &FnArg) -> syn::Stmt {
     match input {
@@ -41,7 +62,11 @@ fn translate_to_robj(input: &FnArg) -> syn::Stmt {
             let pat = &pattype.pat.as_ref();
             if let syn::Pat::Ident(ref ident) = pat {
                 let varname = format_ident!("_{}_robj", ident.ident);
-                parse_quote! { let #varname = extendr_api::new_borrowed(#pat); }
+                if mut_slice_elem(&pattype.ty).is_some() {
+                    parse_quote! { let mut #varname = extendr_api::new_borrowed(#pat); }
+                } else {
+                    parse_quote! { let #varname = extendr_api::new_borrowed(#pat); }
+                }
             } else {
                 panic!("expect identifier as arg name")
             }
@@ -60,7 +85,18 @@ fn translate_actual(input: &FnArg) -> Option<Expr> {
             let ty = &pattype.ty.as_ref();
             if let syn::Pat::Ident(ref ident) = pat {
                 let varname = format_ident!("_{}_robj", ident.ident);
-                Some(parse_quote!{ extendr_api::unwrap_or_throw(<#ty>::from_robj(&#varname)) })
+                if let Some(elem) = mut_slice_elem(ty) {
+                    // Output parameter: the function writes into the caller's R vector in place.
+                    // This bypasses R's copy on modify, so the vector must not be shared.
+                    Some(parse_quote!{ {
+                        let mismatch = extendr_api::AsTypedSlice::<#elem>::typed_slice_mismatch(&#varname);
+                        extendr_api::unwrap_or_throw(
+                            extendr_api::AsTypedSlice::<#elem>::as_typed_slice_mut(&mut #varname).ok_or(mismatch)
+                        )
+                    } })
+                } else {
+                    Some(parse_quote!{ extendr_api::unwrap_or_throw(<#ty>::from_robj(&#varname)) })
+                }
             } else {
                 None
             }
@@ -73,23 +109,29 @@ fn translate_actual(input: &FnArg) -> Option<Expr> {
 }
 
 /// Parse a set of attribute arguments for #[extendr(opts...)]
-fn parse_options(_opts: &mut ExtendrOptions, _arg: &syn::NestedMeta) {
-    /*use syn::{Lit, Meta, MetaNameValue, NestedMeta};
+fn parse_options(opts: &mut ExtendrOptions, arg: &syn::NestedMeta) {
+    use syn::{Meta, NestedMeta};
 
     match arg {
-        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
-            ref path,
-            eq_token: _,
-            lit: Lit::Str(ref _lit_str),
-        })) => {
-        }
-        _ => panic!("expected #[extendr(opt = \"string\", ...)]"),
-    }*/
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("r_print_debug") => {
+            opts.r_print_debug = true;
+        }
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("r_length") => {
+            opts.r_length = true;
+        }
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("r_index") => {
+            opts.r_index = true;
+        }
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("expose_fields") => {
+            opts.expose_fields = true;
+        }
+        _ => panic!("expected #[extendr(r_print_debug, r_length, r_index, expose_fields)]"),
+    }
 }
 
 /// Generate bindings for a single function.
 fn extendr_function(args: Vec<syn::NestedMeta>, func: ItemFn) -> TokenStream {
-    let mut opts = ExtendrOptions {};
+    let mut opts = ExtendrOptions::default();
 
     for arg in &args {
         parse_options(&mut opts, arg);
@@ -112,8 +154,6 @@ fn generate_wrappers(_opts: &ExtendrOptions, wrappers: &mut Vec<ItemFn>, prefix:
     let wrap_name = format_ident!("{}{}{}", WRAP_PREFIX, prefix, func_name);
     let init_name = format_ident!("{}{}{}", INIT_PREFIX, prefix, func_name);
 
-    let wrap_name_str = format!("{}", wrap_name);
-
     let inputs = &sig.inputs;
     let has_self = match inputs.iter().next() {
         Some(FnArg::Receiver(_)) => true,
@@ -121,11 +161,23 @@ fn generate_wrappers(_opts: &ExtendrOptions, wrappers: &mut Vec<ItemFn>, prefix:
     };
 
     let call_name = if has_self {
-        let is_mut = match inputs.iter().next() {
-            Some(FnArg::Receiver(ref reciever)) => reciever.mutability.is_some(),
-            _ => false
+        let (is_ref, is_mut) = match inputs.iter().next() {
+            Some(FnArg::Receiver(ref reciever)) => (reciever.reference.is_some(), reciever.mutability.is_some()),
+            _ => (false, false)
         };
-        if is_mut {
+        if !is_ref {
+            // eg. Person::into_name(self)
+            // The value is moved out of the external pointer, which is left null.
+            let self_ty_name = quote! {#self_ty}.to_string();
+            quote! { extendr_api::unwrap_or_throw(
+                if _self_robj.check_external_ptr(#self_ty_name) {
+                    _self_robj.take_external_ptr::<#self_ty>()
+                        .ok_or(concat!(#self_ty_name, " has already been moved"))
+                } else {
+                    Err(concat!("expected ", #self_ty_name))
+                }
+            ).#func_name }
+        } else if is_mut {
             // eg. Person::name(&mut self)
             quote! { extendr_api::unwrap_or_throw(
                 <&mut #self_ty>::from_robj(&_self_robj)
@@ -164,23 +216,38 @@ fn generate_wrappers(_opts: &ExtendrOptions, wrappers: &mut Vec<ItemFn>, prefix:
     //     .filter_map(|input| translate_R_formal(input))
     //     .collect();
 
+    // Functions returning a Result throw an R error on Err.
+    let call_expr = if returns_result(&sig.output) {
+        quote! { extendr_api::unwrap_or_throw(#call_name(#actual_args)) }
+    } else {
+        quote! { #call_name(#actual_args) }
+    };
+
     let num_args = inputs.len() as i32;
 
     wrappers.push(parse_quote!(
         #[no_mangle]
         #[allow(non_snake_case)]
         pub extern "C" fn #wrap_name(#formal_args) -> extendr_api::SEXP {
-            unsafe {
+            // A panic must not unwind into R, so it becomes an R error.
+            extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
                 use extendr_api::FromRobj;
                 #( #convert_args )*
-                extendr_api::Robj::from(#call_name(#actual_args)).get()
-            }
+                extendr_api::Robj::from(#call_expr).get()
+            }))
         }
     ));
 
-    wrappers.push(parse_quote!(
+    wrappers.push(make_init(&init_name, &wrap_name, num_args));
+}
+
+// Generate an init function registering a wrapper with R.
+fn make_init(init_name: &Ident, wrap_name: &Ident, num_args: i32) -> ItemFn {
+    let wrap_name_str = format!("{}", wrap_name);
+
+    parse_quote!(
         #[allow(non_snake_case)]
-        fn #init_name(info: *mut extendr_api::DllInfo, call_methods: &mut Vec<extendr_api::CallMethod>) {
+        pub(crate) fn #init_name(info: *mut extendr_api::DllInfo, call_methods: &mut Vec<extendr_api::CallMethod>) {
             call_methods.push(
                 extendr_api::CallMethod {
                     call_symbol: std::ffi::CString::new(#wrap_name_str).unwrap(),
@@ -189,12 +256,131 @@ fn generate_wrappers(_opts: &ExtendrOptions, wrappers: &mut Vec<ItemFn>, prefix:
                 }
             )
         }
+    )
+}
+
+// Generate a zero argument wrapper returning an associated constant, eg. wrap__Foo__VERSION.
+fn generate_const_wrappers(wrappers: &mut Vec<ItemFn>, prefix: &str, const_name: &Ident, self_ty: &syn::Type) {
+    let wrap_name = format_ident!("{}{}{}", WRAP_PREFIX, prefix, const_name);
+    let init_name = format_ident!("{}{}{}", INIT_PREFIX, prefix, const_name);
+
+    wrappers.push(parse_quote!(
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "C" fn #wrap_name() -> extendr_api::SEXP {
+            extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
+                extendr_api::Robj::from(<#self_ty>::#const_name).get()
+            }))
+        }
     ));
+
+    wrappers.push(make_init(&init_name, &wrap_name, 0));
+}
+
+// Generate a wrapper formatting the value with {:?}, eg. wrap__Foo__print.
+fn generate_print_wrappers(wrappers: &mut Vec<ItemFn>, prefix: &str, self_ty: &syn::Type) {
+    let wrap_name = format_ident!("{}{}print", WRAP_PREFIX, prefix);
+    let init_name = format_ident!("{}{}print", INIT_PREFIX, prefix);
+
+    wrappers.push(parse_quote!(
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "C" fn #wrap_name(_self : extendr_api::SEXP) -> extendr_api::SEXP {
+            extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
+                use extendr_api::FromRobj;
+                let _self_robj = extendr_api::new_borrowed(_self);
+                let value = extendr_api::unwrap_or_throw(<&#self_ty>::from_robj(&_self_robj));
+                extendr_api::Robj::from(format!("{:?}", value).as_str()).get()
+            }))
+        }
+    ));
+
+    wrappers.push(make_init(&init_name, &wrap_name, 1));
+}
+
+// Generate wrappers for the length and [[ S3 methods of a collection,
+// eg. wrap__Foo__length and wrap__Foo__index.
+fn generate_collection_wrappers(opts: &ExtendrOptions, wrappers: &mut Vec<ItemFn>, prefix: &str, self_ty: &syn::Type) {
+    if opts.r_length {
+        let wrap_name = format_ident!("{}{}length", WRAP_PREFIX, prefix);
+        let init_name = format_ident!("{}{}length", INIT_PREFIX, prefix);
+
+        wrappers.push(parse_quote!(
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub extern "C" fn #wrap_name(_self : extendr_api::SEXP) -> extendr_api::SEXP {
+                extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
+                    use extendr_api::FromRobj;
+                    let _self_robj = extendr_api::new_borrowed(_self);
+                    let value = extendr_api::unwrap_or_throw(<&#self_ty>::from_robj(&_self_robj));
+                    extendr_api::Robj::from(value.len()).get()
+                }))
+            }
+        ));
+
+        wrappers.push(make_init(&init_name, &wrap_name, 1));
+    }
+
+    if opts.r_index {
+        let wrap_name = format_ident!("{}{}index", WRAP_PREFIX, prefix);
+        let init_name = format_ident!("{}{}index", INIT_PREFIX, prefix);
+
+        // R indices start at one.
+        wrappers.push(parse_quote!(
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub extern "C" fn #wrap_name(_self : extendr_api::SEXP, i : extendr_api::SEXP) -> extendr_api::SEXP {
+                extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
+                    use extendr_api::FromRobj;
+                    let _self_robj = extendr_api::new_borrowed(_self);
+                    let _i_robj = extendr_api::new_borrowed(i);
+                    let value = extendr_api::unwrap_or_throw(<&#self_ty>::from_robj(&_self_robj));
+                    let i = extendr_api::unwrap_or_throw(<i32>::from_robj(&_i_robj));
+                    if i < 1 {
+                        extendr_api::throw_r_error("subscript out of bounds");
+                    }
+                    match value.get(i as usize - 1) {
+                        Some(elt) => extendr_api::Robj::from(elt).get(),
+                        None => extendr_api::throw_r_error("subscript out of bounds"),
+                    }
+                }))
+            }
+        ));
+
+        wrappers.push(make_init(&init_name, &wrap_name, 2));
+    }
+}
+
+// Return true if the return type is Result<T, E> (or an alias such as io::Result<T>).
+fn returns_result(output: &syn::ReturnType) -> bool {
+    if let syn::ReturnType::Type(_, ref ty) = output {
+        if let syn::Type::Path(ref type_path) = ty.as_ref() {
+            if let Some(segment) = type_path.path.segments.last() {
+                return segment.ident == "Result";
+            }
+        }
+    }
+    false
 }
 
 /// Handle trait implementations.
-fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
-    let opts = ExtendrOptions {};
+fn extendr_impl(args: Vec<syn::NestedMeta>, mut item_impl: ItemImpl) -> TokenStream {
+    // The wrappers convert to and from external pointers tagged with the type name,
+    // so there is no way to recover generic or lifetime parameters on the R side.
+    if !item_impl.generics.params.is_empty() {
+        let err = syn::Error::new_spanned(
+            &item_impl.generics,
+            "#[extendr] impl blocks cannot have generic or lifetime parameters",
+        );
+        return TokenStream::from(err.to_compile_error());
+    }
+
+    let mut opts = ExtendrOptions::default();
+
+    for arg in &args {
+        parse_options(&mut opts, arg);
+    }
+
     let self_ty = item_impl.self_ty.as_ref();
     let self_ty_name = quote! {#self_ty}.to_string();
     let prefix = format!("{}__", self_ty_name);
@@ -204,9 +390,27 @@ fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
         if let syn::ImplItem::Method(ref mut method) = impl_item {
             method_init_names.push(format_ident!("{}{}__{}", INIT_PREFIX, self_ty_name, method.sig.ident));
             generate_wrappers(&opts, &mut wrappers, prefix.as_str(), &method.sig, Some(self_ty));
+        } else if let syn::ImplItem::Const(ref constant) = impl_item {
+            method_init_names.push(format_ident!("{}{}__{}", INIT_PREFIX, self_ty_name, constant.ident));
+            generate_const_wrappers(&mut wrappers, prefix.as_str(), &constant.ident, self_ty);
         }
     }
 
+    if opts.r_print_debug {
+        method_init_names.push(format_ident!("{}{}__print", INIT_PREFIX, self_ty_name));
+        generate_print_wrappers(&mut wrappers, prefix.as_str(), self_ty);
+    }
+
+    if opts.r_length {
+        method_init_names.push(format_ident!("{}{}__length", INIT_PREFIX, self_ty_name));
+    }
+
+    if opts.r_index {
+        method_init_names.push(format_ident!("{}{}__index", INIT_PREFIX, self_ty_name));
+    }
+
+    generate_collection_wrappers(&opts, &mut wrappers, prefix.as_str(), self_ty);
+
     let init_name = format_ident!("{}{}", INIT_PREFIX, self_ty_name);
 
     let finalizer_name = format_ident!("__finalize__{}", self_ty_name);
@@ -219,7 +423,13 @@ fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
         impl<'a> extendr_api::FromRobj<'a> for &#self_ty {
             fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
                 if robj.check_external_ptr(#self_ty_name) {
-                    Ok(unsafe { std::mem::transmute(robj.externalPtrAddr::<#self_ty>()) })
+                    let ptr = unsafe { robj.externalPtrAddr::<#self_ty>() };
+                    // The pointer is cleared by methods that take self by value.
+                    if ptr.is_null() {
+                        Err(concat!(#self_ty_name, " has already been moved"))
+                    } else {
+                        Ok(unsafe { std::mem::transmute(ptr) })
+                    }
                 } else {
                     Err(concat!("expected ", #self_ty_name))
                 }
@@ -229,7 +439,13 @@ fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
         impl<'a> extendr_api::FromRobj<'a> for &mut #self_ty {
             fn from_robj(robj: &'a Robj) -> Result<Self, &'static str> {
                 if robj.check_external_ptr(#self_ty_name) {
-                    Ok(unsafe { std::mem::transmute(robj.externalPtrAddr::<#self_ty>()) })
+                    let ptr = unsafe { robj.externalPtrAddr::<#self_ty>() };
+                    // The pointer is cleared by methods that take self by value.
+                    if ptr.is_null() {
+                        Err(concat!(#self_ty_name, " has already been moved"))
+                    } else {
+                        Ok(unsafe { std::mem::transmute(ptr) })
+                    }
                 } else {
                     Err(concat!("expected ", #self_ty_name))
                 }
@@ -253,13 +469,16 @@ fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
                 if robj.check_external_ptr(#self_ty_name) {
                     //eprintln!("finalize {}", #self_ty_name);
                     let ptr = robj.externalPtrAddr::<#self_ty>();
-                    Box::from_raw(ptr);
+                    // The pointer is cleared if the value has been taken.
+                    if !ptr.is_null() {
+                        drop(Box::from_raw(ptr));
+                    }
                 }
             }
         }
 
         #[allow(non_snake_case)]
-        fn #init_name(info: *mut extendr_api::DllInfo, call_methods: &mut Vec<extendr_api::CallMethod>) {
+        pub(crate) fn #init_name(info: *mut extendr_api::DllInfo, call_methods: &mut Vec<extendr_api::CallMethod>) {
             #( #method_init_names(info, call_methods); )*
         }
     });
@@ -268,13 +487,90 @@ fn extendr_impl(mut item_impl: ItemImpl) -> TokenStream {
     expanded
 }
 
+/// Generate accessors for the pub fields of a struct.
+fn extendr_struct(args: Vec<syn::NestedMeta>, item_struct: syn::ItemStruct) -> TokenStream {
+    let mut opts = ExtendrOptions::default();
+
+    for arg in &args {
+        parse_options(&mut opts, arg);
+    }
+
+    if !opts.expose_fields {
+        return TokenStream::from(quote! { #item_struct });
+    }
+
+    let self_ty = &item_struct.ident;
+    let prefix = format!("{}__", self_ty);
+    let mut field_init_names = Vec::new();
+    let mut wrappers: Vec<ItemFn> = Vec::new();
+    for field in &item_struct.fields {
+        if let (syn::Visibility::Public(_), Some(ref field_name)) = (&field.vis, &field.ident) {
+            let wrap_name = format_ident!("{}{}{}", WRAP_PREFIX, prefix, field_name);
+            let init_name = format_ident!("{}{}{}", INIT_PREFIX, prefix, field_name);
+
+            wrappers.push(parse_quote!(
+                #[no_mangle]
+                #[allow(non_snake_case)]
+                pub extern "C" fn #wrap_name(_self : extendr_api::SEXP) -> extendr_api::SEXP {
+                    extendr_api::unwrap_or_throw(extendr_api::catch_panic(|| unsafe {
+                        use extendr_api::FromRobj;
+                        let _self_robj = extendr_api::new_borrowed(_self);
+                        let value = extendr_api::unwrap_or_throw(<&#self_ty>::from_robj(&_self_robj));
+                        extendr_api::Robj::from(value.#field_name.clone()).get()
+                    }))
+                }
+            ));
+
+            wrappers.push(make_init(&init_name, &wrap_name, 1));
+            field_init_names.push(init_name);
+        }
+    }
+
+    let init_name = format_ident!("{}{}__fields", INIT_PREFIX, self_ty);
+
+    TokenStream::from(quote! {
+        #item_struct
+
+        #( #wrappers )*
+
+        #[allow(non_snake_case)]
+        pub(crate) fn #init_name(info: *mut extendr_api::DllInfo, call_methods: &mut Vec<extendr_api::CallMethod>) {
+            #( #field_init_names(info, call_methods); )*
+        }
+    })
+}
+
 /// Generate bindings for a single function.
+///
+/// Functions and methods returning `Result<T, E>` where `E: Display`
+/// throw an R error with the message of `E` on `Err`.
+///
+/// Arguments of type `&mut [T]` are output parameters: the function writes into the
+/// R vector in place, bypassing R's copy on modify. Only pass a vector that is not
+/// bound elsewhere, eg. `numeric(n)` made for the call.
+///
+/// Methods taking `self` by value move the value out of the external pointer,
+/// so any later method call on the R object throws "Foo has already been moved".
+///
+/// Impl blocks cannot have generic or lifetime parameters, eg. `impl<'a> Foo<'a>`.
+///
+/// `#[extendr(r_print_debug)]` on an impl block also generates `wrap__Foo__print`,
+/// which returns the value formatted with `{:?}` for use in a `print.Foo` method.
+///
+/// `#[extendr(r_length, r_index)]` generates `wrap__Foo__length` and `wrap__Foo__index`
+/// for `length.Foo` and `[[.Foo` methods. These call `fn len(&self) -> usize` and
+/// `fn get(&self, i: usize) -> Option<Robj>` with a zero based index.
+///
+/// `#[extendr(expose_fields)]` on a struct with an `#[extendr] impl` generates an
+/// accessor `wrap__Foo__x` for each pub field `x`. The field types must be `Clone`
+/// and convert into `Robj`. Register them with `struct Foo;` in `extendr_module!`.
 #[proc_macro_attribute]
 pub fn extendr(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attr as syn::AttributeArgs);
     match parse_macro_input!(item as Item) {
         Item::Fn(func) => return extendr_function(args, func),
-        Item::Impl(item_impl) => return extendr_impl(item_impl),
+        Item::Impl(item_impl) => return extendr_impl(args, item_impl),
+        Item::Struct(item_struct) => return extendr_struct(args, item_struct),
         other_item => {
             TokenStream::from(quote! {#other_item})
         }
@@ -286,36 +582,116 @@ struct Module {
     modname: Option<Ident>,
     fnnames: Vec<Ident>,
     implnames: Vec<Ident>,
+    structnames: Vec<Ident>,
+    submodules: Vec<Module>,
 }
 
-impl syn::parse::Parse for Module {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        use syn::spanned::Spanned;
-        let mut res = Self {
-            modname: None,
+impl Module {
+    fn new(modname: Option<Ident>) -> Self {
+        Self {
+            modname,
             fnnames: Vec::new(),
             implnames: Vec::new(),
-        };
+            structnames: Vec::new(),
+            submodules: Vec::new(),
+        }
+    }
+
+    // Parse the entries of a module. Nested modules are written as `mod name { ... }`.
+    fn parse_entries(&mut self, input: ParseStream, nested: bool) -> syn::Result<()> {
+        use syn::spanned::Spanned;
         while !input.is_empty() {
             if let Ok(kmod) = input.parse::<Token![mod]>() {
                 let name : Ident = input.parse()?;
-                if !res.modname.is_none() {
+                if input.peek(syn::token::Brace) {
+                    let content;
+                    syn::braced!(content in input);
+                    let mut submodule = Module::new(Some(name));
+                    submodule.parse_entries(&content, true)?;
+                    self.submodules.push(submodule);
+                    continue;
+                }
+                if nested {
+                    return Err(syn::Error::new(kmod.span(), "expected mod name { ... } in a nested module"));
+                }
+                if !self.modname.is_none() {
                     return Err(syn::Error::new(kmod.span(), "only one mod allowed"));
                 }
-                res.modname = Some(name);
+                self.modname = Some(name);
             } else if let Ok(_) = input.parse::<Token![fn]>() {
-                res.fnnames.push(input.parse()?);
+                self.fnnames.push(input.parse()?);
             } else if let Ok(_) = input.parse::<Token![impl]>() {
-                res.implnames.push(input.parse()?);
+                self.implnames.push(input.parse()?);
+            } else if let Ok(_) = input.parse::<Token![struct]>() {
+                self.structnames.push(input.parse()?);
             } else {
-                return Err(syn::Error::new(input.span(), "expected mod, fn or impl"));
+                return Err(syn::Error::new(input.span(), "expected mod, fn, impl or struct"));
             }
 
             input.parse::<Token![;]>()?;
         }
+        Ok(())
+    }
+
+    // The wrapper symbols are not prefixed by the module, so a fn, impl or struct may only
+    // be exported once across all the nested modules.
+    fn check_unique(&self, seen: &mut Vec<String>) -> syn::Result<()> {
+        let fns = self.fnnames.iter().map(|id| ("fn", id));
+        let impls = self.implnames.iter().map(|id| ("impl", id));
+        let structs = self.structnames.iter().map(|id| ("struct", id));
+        for (kind, id) in fns.chain(impls).chain(structs) {
+            let name = format!("{} {}", kind, id);
+            if seen.contains(&name) {
+                return Err(syn::Error::new(id.span(), format!("{} is exported more than once; names must be unique across nested modules", name)));
+            }
+            seen.push(name);
+        }
+        for submodule in &self.submodules {
+            submodule.check_unique(seen)?;
+        }
+        Ok(())
+    }
+
+    // Generate calls to the init functions of this module.
+    // Functions in nested modules are registered with the module name after the wrapper prefix,
+    // eg. wrap__math__add for `mod math { fn add; }`.
+    fn init_calls(&self, path: &[&Ident]) -> Vec<syn::Stmt> {
+        let fninitnames = self.fnnames.iter().map(|id| format_ident!("{}{}", INIT_PREFIX, id));
+        let implinitnames = self.implnames.iter().map(|id| format_ident!("{}{}", INIT_PREFIX, id));
+        let structinitnames = self.structnames.iter().map(|id| format_ident!("{}{}__fields", INIT_PREFIX, id));
+
+        let mut calls: Vec<syn::Stmt> = fninitnames
+            .chain(implinitnames)
+            .chain(structinitnames)
+            .map(|init_name| parse_quote! { #( #path:: )* #init_name(info, &mut call_methods); })
+            .collect();
+
+        for submodule in &self.submodules {
+            let name = submodule.modname.as_ref().unwrap();
+            let mut subpath = path.to_vec();
+            subpath.push(name);
+            let subcalls = submodule.init_calls(&subpath);
+            let prefix = format!("{}__", name);
+            calls.push(parse_quote! {
+                {
+                    let start = call_methods.len();
+                    #( #subcalls )*
+                    extendr_api::prefix_call_methods(&mut call_methods[start..], #WRAP_PREFIX, #prefix);
+                }
+            });
+        }
+        calls
+    }
+}
+
+impl syn::parse::Parse for Module {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut res = Module::new(None);
+        res.parse_entries(input, false)?;
         if res.modname.is_none() {
             return Err(syn::Error::new(input.span(), "expected one 'mod name'"));
         }
+        res.check_unique(&mut Vec::new())?;
         Ok(res)
     }
 }
@@ -329,25 +705,41 @@ impl syn::parse::Parse for Module {
 ///     fn my_func1;
 ///     fn my_func2;
 ///     impl MyTrait;
+///     struct MyStruct;
+///     mod math {
+///         fn add;
+///     }
 /// }
+///
+/// Functions in a nested module are found in the Rust module of the same name
+/// and are registered with the module name as a prefix, eg. `wrap__math__add`.
+/// Only the registered name is prefixed: the C symbol is still `wrap__add`, so a name
+/// may only be exported once across all the nested modules, and the unprefixed symbol
+/// can still be found by `.Call` while dynamic symbol lookup is enabled.
+///
+/// `struct MyStruct;` registers the field accessors of `#[extendr(expose_fields)]`.
 /// 
 #[proc_macro]
 pub fn extendr_module(item: TokenStream) -> TokenStream {
     let module = parse_macro_input!(item as Module);
-    let Module {modname, fnnames, implnames} = module;
-    let modname = modname.unwrap();
+    let modname = module.modname.as_ref().unwrap();
     let module_init_name = format_ident!("R_init_lib{}", modname);
+    let call_methods_name = format_ident!("get_{}_call_methods", modname);
 
-    let fninitnames = fnnames.iter().map(|id| format_ident!("{}{}", INIT_PREFIX, id));
-    let implinitnames = implnames.iter().map(|id| format_ident!("{}{}", INIT_PREFIX, id));
+    let init_calls = module.init_calls(&[]);
 
     TokenStream::from(quote!{
+        #[allow(non_snake_case)]
+        fn #call_methods_name(info: * mut extendr_api::DllInfo) -> Vec<extendr_api::CallMethod> {
+            let mut call_methods = Vec::new();
+            #( #init_calls )*
+            call_methods
+        }
+
         #[no_mangle]
         #[allow(non_snake_case)]
         pub extern "C" fn #module_init_name(info: * mut extendr_api::DllInfo) {
-            let mut call_methods = Vec::new();
-            #( #fninitnames(info, &mut call_methods); )*
-            #( #implinitnames(info, &mut call_methods); )*
+            let call_methods = #call_methods_name(info);
             unsafe { extendr_api::register_call_methods(info, call_methods.as_ref()) };
         }
     })